@@ -0,0 +1,137 @@
+//! Borrowed arguments, as passed by the extension, against owned `String`s copied into one buffer
+//! before hashing, as the extension did before.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use pg_row_hashes_fingerprint::{
+    checksum_fingerprint, id_fingerprint, Algorithm, Farmhash, NullPolicy, Seahash,
+};
+
+/// The encoding as implemented before, copying every argument into `Bytes`.
+mod owned {
+    use bytes::{BufMut, Bytes, BytesMut};
+    use itertools::Itertools;
+
+    pub fn id_bytes(ids: Vec<String>) -> Bytes {
+        #[allow(unstable_name_collisions)] // silence warning about intersperse
+        let vec: Vec<Bytes> = ids
+            .into_iter()
+            .map(Bytes::from)
+            .intersperse(Bytes::from_static(&[0]))
+            .collect();
+        vec.concat().into()
+    }
+
+    /// `checksum_bytes` keeping `NULL` values.
+    pub fn checksum_bytes(pairs: Vec<Option<String>>) -> Bytes {
+        let mut vec: Vec<(Option<Bytes>, Option<Bytes>)> = pairs
+            .into_iter()
+            .map(|e| e.map(Bytes::from))
+            .tuples()
+            .collect();
+        vec.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        vec.dedup();
+
+        #[allow(unstable_name_collisions)] // silence warning about intersperse
+        let vec: Vec<Bytes> = vec
+            .into_iter()
+            .filter_map(|(key, value)| Some(join_tuple(key?, value.unwrap_or_default())))
+            .intersperse(Bytes::from_static(&[0]))
+            .collect();
+        vec.concat().into()
+    }
+
+    fn join_tuple(a: Bytes, b: Bytes) -> Bytes {
+        let mut buf = BytesMut::with_capacity(a.len() + b.len() + 1);
+        buf.put(a);
+        buf.put_u8(0);
+        buf.put(b);
+        buf.freeze()
+    }
+}
+
+/// Key, value pairs of `width` columns, like a row passed to `checksum_*`.
+fn row(width: usize) -> Vec<Option<String>> {
+    (0..width)
+        .flat_map(|i| {
+            [
+                Some(format!("column_{i}")),
+                Some(format!("value of column {i}")),
+            ]
+        })
+        .collect()
+}
+
+fn bench_checksum(c: &mut Criterion) {
+    let mut group = c.benchmark_group("checksum");
+    for width in [4, 16, 64] {
+        let row = row(width);
+        let borrowed: Vec<Option<&str>> = row.iter().map(Option::as_deref).collect();
+        group.throughput(Throughput::Elements(1));
+
+        group.bench_with_input(BenchmarkId::new("farmhash/owned", width), &row, |b, row| {
+            b.iter(|| {
+                Farmhash::fingerprint(&owned::checksum_bytes(black_box(row).clone()))
+            })
+        });
+        group.bench_with_input(
+            BenchmarkId::new("farmhash/borrowed", width),
+            &borrowed,
+            |b, row| {
+                b.iter(|| {
+                    checksum_fingerprint::<Farmhash, _>(
+                        black_box(row).iter().copied(),
+                        NullPolicy::Keep,
+                    )
+                })
+            },
+        );
+        group.bench_with_input(BenchmarkId::new("seahash/owned", width), &row, |b, row| {
+            b.iter(|| {
+                Seahash::fingerprint(&owned::checksum_bytes(black_box(row).clone()))
+            })
+        });
+        group.bench_with_input(
+            BenchmarkId::new("seahash/borrowed", width),
+            &borrowed,
+            |b, row| {
+                b.iter(|| {
+                    checksum_fingerprint::<Seahash, _>(
+                        black_box(row).iter().copied(),
+                        NullPolicy::Keep,
+                    )
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn bench_id(c: &mut Criterion) {
+    let mut group = c.benchmark_group("id");
+    for width in [1, 4, 16] {
+        let ids: Vec<String> = (0..width).map(|i| format!("id part {i}")).collect();
+        let borrowed: Vec<&str> = ids.iter().map(String::as_str).collect();
+        group.throughput(Throughput::Elements(1));
+
+        group.bench_with_input(BenchmarkId::new("farmhash/owned", width), &ids, |b, ids| {
+            b.iter(|| Farmhash::fingerprint(&owned::id_bytes(black_box(ids).clone())))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("farmhash/borrowed", width),
+            &borrowed,
+            |b, ids| b.iter(|| id_fingerprint::<Farmhash, _>(black_box(ids).iter())),
+        );
+        group.bench_with_input(BenchmarkId::new("seahash/owned", width), &ids, |b, ids| {
+            b.iter(|| Seahash::fingerprint(&owned::id_bytes(black_box(ids).clone())))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("seahash/borrowed", width),
+            &borrowed,
+            |b, ids| b.iter(|| id_fingerprint::<Seahash, _>(black_box(ids).iter())),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_checksum, bench_id);
+criterion_main!(benches);
@@ -0,0 +1,60 @@
+//! Throughput of the canonical encoding and of the hash algorithms on their own.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use pg_row_hashes_fingerprint::{
+    checksum_bytes, id_bytes, pair_bytes, Algorithm, Farmhash, NullPolicy, Seahash,
+};
+
+/// Key, value pairs of `width` columns in reverse order, every fourth value `NULL`.
+fn row(width: usize) -> Vec<Option<String>> {
+    (0..width)
+        .rev()
+        .flat_map(|i| {
+            let value = (i % 4 != 0).then(|| format!("value of column {i}"));
+            [Some(format!("column_{i:03}")), value]
+        })
+        .collect()
+}
+
+fn bench_encoding(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encoding");
+    for width in [1, 4, 16, 64] {
+        let row = row(width);
+        let row: Vec<Option<&str>> = row.iter().map(Option::as_deref).collect();
+        let ids: Vec<&str> = row.iter().step_by(2).flatten().copied().collect();
+        group.throughput(Throughput::Elements(1));
+
+        group.bench_with_input(BenchmarkId::new("id_bytes", width), &ids, |b, ids| {
+            b.iter(|| id_bytes(black_box(ids)))
+        });
+        for nulls in [NullPolicy::Keep, NullPolicy::Skip] {
+            let name = format!("checksum_bytes/{nulls:?}");
+            group.bench_with_input(BenchmarkId::new(name, width), &row, |b, row| {
+                b.iter(|| checksum_bytes(black_box(row).iter().copied(), nulls))
+            });
+        }
+        group.bench_with_input(BenchmarkId::new("pair_bytes", width), &row, |b, row| {
+            b.iter(|| pair_bytes(black_box(row).iter().copied(), NullPolicy::Keep))
+        });
+    }
+    group.finish();
+}
+
+fn bench_hash(c: &mut Criterion) {
+    let mut group = c.benchmark_group("hash");
+    for len in [16, 256, 4096, 65536] {
+        let bytes: Vec<u8> = (0..len).map(|i| i as u8).collect();
+        group.throughput(Throughput::Bytes(len as u64));
+
+        group.bench_with_input(BenchmarkId::new("farmhash", len), &bytes, |b, bytes| {
+            b.iter(|| Farmhash::fingerprint(black_box(bytes)))
+        });
+        group.bench_with_input(BenchmarkId::new("seahash", len), &bytes, |b, bytes| {
+            b.iter(|| Seahash::fingerprint(black_box(bytes)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_encoding, bench_hash);
+criterion_main!(benches);
@@ -0,0 +1,144 @@
+//! Golden fingerprints shared by the tests of this crate and the extension.
+
+use bytes::Bytes;
+
+/// Key, value pairs with their fingerprints:
+/// `checksum_farmhash_extendable`, `checksum_seahash_extendable`, `checksum_farmhash`, `checksum_seahash`.
+pub type ChecksumGolden = (&'static [Option<&'static str>], u128, u64, u128, u64);
+
+pub static CHECKSUM_GOLDEN_TABLE: [ChecksumGolden; 7] = [
+    (
+        &[
+            Some("b"),
+            Some("1"),
+            Some("a"),
+            Some("2"),
+            Some("c"),
+            Some("3"),
+        ],
+        200872945134416140889070688363161139169,
+        2409718014940744358,
+        200872945134416140889070688363161139169,
+        2409718014940744358,
+    ),
+    (
+        &[
+            Some("c"),
+            Some("1"),
+            Some("b"),
+            Some("2"),
+            Some("a"),
+            Some("3"),
+        ],
+        159497434488907202746785405570285430728,
+        13904897432444956006,
+        159497434488907202746785405570285430728,
+        13904897432444956006,
+    ),
+    (
+        &[
+            Some("a"),
+            Some("1"),
+            Some("b"),
+            Some("2"),
+            Some("c"),
+            Some("3"),
+        ],
+        63991421267038837894546220157490372611,
+        2987942282093369026,
+        63991421267038837894546220157490372611,
+        2987942282093369026,
+    ),
+    (
+        &[
+            Some("c"),
+            Some("3"),
+            Some("b"),
+            Some("2"),
+            Some("a"),
+            Some("1"),
+        ],
+        63991421267038837894546220157490372611,
+        2987942282093369026,
+        63991421267038837894546220157490372611,
+        2987942282093369026,
+    ),
+    (
+        &[
+            Some("d"),
+            None,
+            Some("b"),
+            Some("1"),
+            Some("a"),
+            Some("2"),
+            Some("c"),
+            Some("3"),
+        ],
+        200872945134416140889070688363161139169,
+        2409718014940744358,
+        81995647272375524016145587428893207932,
+        1483524512128736869,
+    ),
+    (
+        &[
+            Some("b"),
+            Some("1"),
+            Some("e,"),
+            None,
+            Some("a"),
+            Some("2"),
+            Some("c"),
+            Some("3"),
+            Some("d"),
+            None,
+        ],
+        200872945134416140889070688363161139169,
+        2409718014940744358,
+        25535654360990964780919159173614512903,
+        2918039635165515565,
+    ),
+    (
+        &[
+            Some("a"),
+            Some("1"),
+            Some("d"),
+            None,
+            Some("b"),
+            Some("2"),
+            Some("c"),
+            Some("3"),
+        ],
+        63991421267038837894546220157490372611,
+        2987942282093369026,
+        55229287721383068868671312639375865447,
+        13930034059081198257,
+    ),
+];
+
+/// Ids with their canonical encoding and fingerprints: `id_farmhash`, `id_seahash`.
+pub static ID_GOLDEN_TABLE: [(Bytes, &[&str], u128, u64); 4] = [
+    (
+        Bytes::from_static(b"a\0b\0c"),
+        &["a", "b", "c"],
+        185254626185375829619130502206294491400,
+        14302911629075895706,
+    ),
+    (
+        Bytes::from_static(b"a\0bc"),
+        &["a", "bc"],
+        209923663634918632141264334831867734826,
+        11633404322457790885,
+    ),
+    (
+        Bytes::from_static(b"ab\0c"),
+        &["ab", "c"],
+        48977107076008525069529680716651780466,
+        15405044104555301515,
+    ),
+    (
+        Bytes::from_static(b"ab\0c\0d"),
+        &["ab", "c", "d"],
+        236038958675336168148183549336891112308,
+        15729725979387502697,
+    ),
+];
@@ -0,0 +1,79 @@
+//! Hexadecimal rendering of fingerprints, like `fingerprint_to_hex` and `fingerprint_from_hex` of
+//! the extension.
+//!
+//! Fingerprints are rendered as the unsigned integers the algorithms return, zero padded: 16
+//! digits for seahash, 32 for farmhash. That is neither the signed `bigint` nor the `uuid` text,
+//! which shows farmhash's bytes in little endian order.
+//!
+//! ```
+//! use pg_row_hashes_fingerprint::hex::{from_hex64, to_hex64};
+//! use pg_row_hashes_fingerprint::{id_fingerprint, Seahash};
+//!
+//! let id = id_fingerprint::<Seahash, _>(["a", "b", "c"]);
+//! // SELECT fingerprint_to_hex(id_seahash('a', 'b', 'c'));
+//! assert_eq!(to_hex64(id), "c67e2658b8df559a");
+//! assert_eq!(from_hex64("c67e2658b8df559a"), Some(id));
+//! ```
+
+/// Hexadecimal of a seahash fingerprint, 16 lowercase digits.
+#[inline]
+pub fn to_hex64(fingerprint: u64) -> String {
+    format!("{fingerprint:016x}")
+}
+
+/// Hexadecimal of a farmhash fingerprint, 32 lowercase digits.
+#[inline]
+pub fn to_hex128(fingerprint: u128) -> String {
+    format!("{fingerprint:032x}")
+}
+
+/// Seahash fingerprint of at most 16 hexadecimal digits in any case, with an optional `0x` prefix.
+pub fn from_hex64(hex: &str) -> Option<u64> {
+    digits(hex, 16).and_then(|digits| u64::from_str_radix(digits, 16).ok())
+}
+
+/// Farmhash fingerprint of at most 32 hexadecimal digits in any case, with an optional `0x` prefix.
+pub fn from_hex128(hex: &str) -> Option<u128> {
+    digits(hex, 32).and_then(|digits| u128::from_str_radix(digits, 16).ok())
+}
+
+/// The digits of `hex`, if there are 1 to `max` and nothing else.
+fn digits(hex: &str, max: usize) -> Option<&str> {
+    let digits = hex
+        .strip_prefix("0x")
+        .or_else(|| hex.strip_prefix("0X"))
+        .unwrap_or(hex);
+    (!digits.is_empty() && digits.len() <= max && digits.bytes().all(|b| b.is_ascii_hexdigit()))
+        .then_some(digits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_hex128, from_hex64, to_hex128, to_hex64};
+
+    #[test]
+    fn test_hex64() {
+        for value in [0, 1, 0xff, i64::MAX as u64, 1 << 63, u64::MAX] {
+            assert_eq!(from_hex64(&to_hex64(value)), Some(value));
+        }
+        assert_eq!(to_hex64(255), "00000000000000ff");
+        assert_eq!(to_hex64(u64::MAX), "ffffffffffffffff");
+        assert_eq!(from_hex64("0xFF"), Some(255));
+        assert_eq!(from_hex64("ff"), Some(255));
+        for invalid in ["", "0x", "+ff", "-1", "g", " ff", "1ffffffffffffffff"] {
+            assert_eq!(from_hex64(invalid), None, "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn test_hex128() {
+        for value in [0, 1, u64::MAX as u128, 1 << 127, u128::MAX] {
+            assert_eq!(from_hex128(&to_hex128(value)), Some(value));
+        }
+        assert_eq!(to_hex128(1), "00000000000000000000000000000001");
+        assert_eq!(from_hex128("0X0a"), Some(10));
+        for invalid in ["", "+1", "1ffffffffffffffffffffffffffffffff"] {
+            assert_eq!(from_hex128(invalid), None, "{invalid:?}");
+        }
+    }
+}
@@ -0,0 +1,547 @@
+//! Fingerprints bit-identical to the ones of the `pg_row_hashes` PostgreSQL extension.
+//!
+//! ```
+//! use pg_row_hashes_fingerprint::{checksum_fingerprint, id_fingerprint, Farmhash, NullPolicy, Seahash};
+//!
+//! // SELECT id_seahash('a', 'b', 'c');
+//! let id = id_fingerprint::<Seahash, _>(["a", "b", "c"]);
+//! assert_eq!(id as i64, -4143832444633655910);
+//!
+//! // SELECT checksum_farmhash_extendable('key1', 'value', 'key2', NULL);
+//! let checksum = checksum_fingerprint::<Farmhash, _>(
+//!     [Some("key1"), Some("value"), Some("key2"), None],
+//!     NullPolicy::Skip,
+//! );
+//! assert_eq!(checksum, checksum_fingerprint::<Farmhash, _>([Some("key1"), Some("value")], NullPolicy::Skip));
+//! ```
+
+use std::hash::Hasher;
+
+pub use bytes::Bytes;
+use fasthash::farm;
+use itertools::Itertools;
+
+#[doc(hidden)]
+pub mod golden;
+pub mod hex;
+pub mod partition;
+pub mod sample;
+pub mod shard;
+
+static ZERO_BYTE_ARRAY: [u8; 1] = [0];
+
+/// How `NULL` values of key, value pairs are treated.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum NullPolicy {
+    /// Keep keys with `NULL` values, like `checksum_*`.
+    #[default]
+    Keep,
+    /// Skip keys with `NULL` values, like `checksum_*_extendable`.
+    Skip,
+}
+
+/// Hash algorithm applied to the canonical encoding.
+pub trait Algorithm {
+    type Output;
+
+    fn fingerprint(bytes: &[u8]) -> Self::Output;
+
+    /// Fingerprint of the concatenation of `parts`.
+    ///
+    /// Algorithms hashing incrementally hash the parts in place, the others copy them into one
+    /// buffer first.
+    #[inline]
+    fn fingerprint_parts<'a>(parts: impl IntoIterator<Item = &'a [u8]>) -> Self::Output {
+        let mut buf = Vec::new();
+        for part in parts {
+            buf.extend_from_slice(part);
+        }
+        Self::fingerprint(&buf)
+    }
+}
+
+/// farmhash's fingerprint128, as returned by the `*_farmhash` functions.
+///
+/// The `uuid` is `Uuid::from_bytes(output.to_le_bytes())`.
+pub struct Farmhash;
+
+impl Algorithm for Farmhash {
+    type Output = u128;
+
+    #[inline]
+    fn fingerprint(bytes: &[u8]) -> u128 {
+        farm::fingerprint128(bytes)
+    }
+}
+
+/// seahash, as returned by the `*_seahash` functions.
+///
+/// The `bigint` is `output as i64`.
+pub struct Seahash;
+
+impl Algorithm for Seahash {
+    type Output = u64;
+
+    #[inline]
+    fn fingerprint(bytes: &[u8]) -> u64 {
+        seahash::hash(bytes)
+    }
+
+    #[inline]
+    fn fingerprint_parts<'a>(parts: impl IntoIterator<Item = &'a [u8]>) -> u64 {
+        let mut hasher = seahash::SeaHasher::new();
+        for part in parts {
+            hasher.write(part);
+        }
+        hasher.finish()
+    }
+}
+
+/// Fingerprint of ids, like `id_farmhash(VARIADIC text[])` and `id_seahash(VARIADIC text[])`.
+///
+/// The ids are only borrowed, e.g. `&str` straight from the arguments.
+#[inline]
+pub fn id_fingerprint<A: Algorithm, T: AsRef<[u8]>>(ids: impl IntoIterator<Item = T>) -> A::Output {
+    let ids: Vec<T> = ids.into_iter().collect();
+    A::fingerprint_parts(id_parts(&ids))
+}
+
+/// Fingerprint of key, value pairs, like `checksum_farmhash(VARIADIC text[])` and
+/// `checksum_seahash(VARIADIC text[])` (or their `_extendable` variants with [`NullPolicy::Skip`]).
+///
+/// `pairs` alternates between keys and values, just like the arguments of the SQL functions.
+/// They are only borrowed, e.g. `&str` straight from the arguments.
+///
+/// This is encoding version 1: the order of pairs with the same key but different values changes
+/// the fingerprint. Up to 20 pairs they stay in argument order, longer inputs are reordered like
+/// the extension always did. See [`checksum_fingerprint_v2`].
+#[inline]
+pub fn checksum_fingerprint<A: Algorithm, T: AsRef<[u8]>>(
+    pairs: impl IntoIterator<Item = Option<T>>,
+    nulls: NullPolicy,
+) -> A::Output {
+    let pairs: Vec<Option<T>> = pairs.into_iter().collect();
+    A::fingerprint_parts(checksum_parts(&normalized_pairs(
+        &pairs,
+        nulls,
+        PairOrder::Key,
+    )))
+}
+
+/// Fingerprint of key, value pairs in encoding version 2, like `checksum_hash(VARIADIC text[])`
+/// with `pg_row_hashes.encoding_version = 2`.
+///
+/// Pairs with the same key are sorted by value, so the fingerprint never depends on the order of
+/// the pairs.
+#[inline]
+pub fn checksum_fingerprint_v2<A: Algorithm, T: AsRef<[u8]>>(
+    pairs: impl IntoIterator<Item = Option<T>>,
+    nulls: NullPolicy,
+) -> A::Output {
+    let pairs: Vec<Option<T>> = pairs.into_iter().collect();
+    A::fingerprint_parts(checksum_parts(&normalized_pairs(
+        &pairs,
+        nulls,
+        PairOrder::KeyValue,
+    )))
+}
+
+/// Canonical encoding of ids: joined by a `\0` byte.
+#[inline]
+pub fn id_bytes<T: AsRef<[u8]>>(ids: impl IntoIterator<Item = T>) -> Bytes {
+    let ids: Vec<T> = ids.into_iter().collect();
+    id_parts(&ids).collect::<Vec<_>>().concat().into()
+}
+
+/// Canonical encoding of key, value pairs: sorted by key, deduplicated and joined by a `\0` byte.
+///
+/// Pairs with the same key are ordered like [`checksum_fingerprint`] does (encoding version 1).
+#[inline]
+pub fn checksum_bytes<T: AsRef<[u8]>>(
+    pairs: impl IntoIterator<Item = Option<T>>,
+    nulls: NullPolicy,
+) -> Bytes {
+    let pairs: Vec<Option<T>> = pairs.into_iter().collect();
+    checksum_parts(&normalized_pairs(&pairs, nulls, PairOrder::Key))
+        .collect::<Vec<_>>()
+        .concat()
+        .into()
+}
+
+/// Canonical encoding of key, value pairs in encoding version 2: sorted by key and value,
+/// deduplicated and joined by a `\0` byte.
+#[inline]
+pub fn checksum_bytes_v2<T: AsRef<[u8]>>(
+    pairs: impl IntoIterator<Item = Option<T>>,
+    nulls: NullPolicy,
+) -> Bytes {
+    let pairs: Vec<Option<T>> = pairs.into_iter().collect();
+    checksum_parts(&normalized_pairs(&pairs, nulls, PairOrder::KeyValue))
+        .collect::<Vec<_>>()
+        .concat()
+        .into()
+}
+
+/// Key, value pairs of the canonical encoding, each joined by a `\0` byte, e.g. to treat a row
+/// as the set of its pairs.
+#[inline]
+pub fn pair_bytes<T: AsRef<[u8]>>(
+    pairs: impl IntoIterator<Item = Option<T>>,
+    nulls: NullPolicy,
+) -> Vec<Bytes> {
+    let pairs: Vec<Option<T>> = pairs.into_iter().collect();
+    normalized_pairs(&pairs, nulls, PairOrder::Key)
+        .into_iter()
+        .map(|(key, value)| [key, &ZERO_BYTE_ARRAY[..], value].concat().into())
+        .collect()
+}
+
+#[inline]
+fn id_parts<T: AsRef<[u8]>>(ids: &[T]) -> impl Iterator<Item = &[u8]> {
+    #[allow(unstable_name_collisions)] // silence warning about intersperse
+    ids.iter()
+        .map(AsRef::as_ref)
+        .intersperse(&ZERO_BYTE_ARRAY)
+}
+
+#[inline]
+fn checksum_parts<'a>(pairs: &'a [Pair<'_>]) -> impl Iterator<Item = &'a [u8]> {
+    pairs.iter().enumerate().flat_map(|(i, (key, value))| {
+        let separator: &[u8] = if i == 0 { &[] } else { &ZERO_BYTE_ARRAY };
+        [separator, *key, &ZERO_BYTE_ARRAY, *value]
+    })
+}
+
+/// Key, value pair, the value empty if it is `NULL`.
+type Pair<'a> = (&'a [u8], &'a [u8]);
+
+/// Key, value pair as passed, `None` if it is `NULL`.
+type ArgumentPair<'a> = (Option<&'a [u8]>, Option<&'a [u8]>);
+
+/// Order of the pairs of the canonical encoding.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum PairOrder {
+    /// By key, pairs with the same key ordered like the released encoding (version 1).
+    Key,
+    /// By key, then by value with `NULL` first (encoding version 2).
+    KeyValue,
+}
+
+/// Key, value pairs sorted by `order` and deduplicated, with `NULL` values handled according to
+/// `nulls`: kept as empty value or skipped. Pairs with a `NULL` key are always skipped.
+///
+/// Keys and values are compared bytewise, a `NULL` key or value before any other.
+#[inline]
+fn normalized_pairs<T: AsRef<[u8]>>(
+    pairs: &[Option<T>],
+    nulls: NullPolicy,
+    order: PairOrder,
+) -> Vec<Pair<'_>> {
+    let mut vec: Vec<ArgumentPair<'_>> = pairs
+        .iter()
+        .map(|e| e.as_ref().map(AsRef::as_ref))
+        .tuples()
+        .collect();
+
+    match order {
+        PairOrder::Key => sort_by_key_v1(&mut vec),
+        // pairs comparing equal are identical, so the sort being unstable doesn't matter
+        PairOrder::KeyValue => vec.sort_unstable(),
+    }
+
+    // filter out same key-value tuples, with `PairOrder::Key` only if they are adjacent
+    vec.dedup();
+
+    vec.into_iter()
+        .filter_map(|pair| filter_pair(pair, nulls))
+        .map(|(key, value)| (key, value.unwrap_or_default()))
+        .collect()
+}
+
+/// Sort pairs by key exactly like encoding version 1 always did: `sort_unstable_by` on owned
+/// `Bytes`. The order it leaves pairs with the same key in depends on the element type, e.g. up to
+/// 20 pairs stay in argument order but longer inputs get reordered.
+///
+/// Pairs are only copied if pairs with the same key differ in value, any other sort by key gives
+/// the same result.
+#[inline]
+fn sort_by_key_v1(pairs: &mut [ArgumentPair<'_>]) {
+    let arguments = pairs.to_vec();
+    pairs.sort_by(|a, b| a.0.cmp(&b.0));
+    if !pairs
+        .iter()
+        .tuple_windows()
+        .any(|(a, b)| a.0 == b.0 && a.1 != b.1)
+    {
+        return;
+    }
+
+    let mut owned: Vec<(Option<Bytes>, Option<Bytes>)> = arguments
+        .into_iter()
+        .map(|(key, value)| {
+            (
+                key.map(Bytes::copy_from_slice),
+                value.map(Bytes::copy_from_slice),
+            )
+        })
+        .collect();
+    owned.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+    // both are sorted by key, so pairs only need to be reordered within the same key
+    for (i, (key, value)) in owned.iter().enumerate() {
+        let j = (i..pairs.len())
+            .find(|&j| pairs[j] == (key.as_deref(), value.as_deref()))
+            .expect("the same pairs");
+        pairs.swap(i, j);
+    }
+}
+
+/// Key and value of a pair to encode, the value `None` if it is `NULL` and kept.
+#[inline]
+fn filter_pair<K>((key, value): (Option<K>, Option<K>), nulls: NullPolicy) -> Option<(K, Option<K>)> {
+    match (key, value, nulls) {
+        (Some(key), Some(value), _) => Some((key, Some(value))),
+        (Some(key), None, NullPolicy::Keep) => Some((key, None)),
+        _ => None,
+    }
+}
+
+/// splitmix64's finalizer, to derive an independent hash from a 64 bit value.
+#[inline]
+pub const fn splitmix64(value: u64) -> u64 {
+    let mut h = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^ (h >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::golden::{CHECKSUM_GOLDEN_TABLE, ID_GOLDEN_TABLE};
+    use crate::{
+        checksum_fingerprint, checksum_fingerprint_v2, id_fingerprint, Farmhash, NullPolicy,
+        Seahash,
+    };
+
+    #[test]
+    fn test_normalized_pairs_keeping_nulls() {
+        let table = [
+            (
+                vec!["a", "v", "b", "v2"],
+                Bytes::from_static(b"a\0v\0b\0v2"),
+            ),
+            (
+                vec!["b", "v2", "a", "v"],
+                Bytes::from_static(b"a\0v\0b\0v2"),
+            ),
+            (
+                vec!["c", "v3", "a", "v", "b", "v2"],
+                Bytes::from_static(b"a\0v\0b\0v2\0c\0v3"),
+            ),
+            (
+                vec!["b", "v2", "c", "v3", "a", "v"],
+                Bytes::from_static(b"a\0v\0b\0v2\0c\0v3"),
+            ),
+        ];
+        for (arr, bytes) in table.iter() {
+            let arr_iter = arr.iter().map(|s| s.to_string()).map(Option::Some);
+            let result = crate::checksum_bytes(arr_iter, NullPolicy::Keep);
+            assert_eq!(result, bytes, "using {:?}", arr);
+        }
+
+        let table = [
+            (
+                vec![Some("c"), None, Some("a"), None, Some("b"), Some("v2")],
+                Bytes::from_static(b"a\0\0b\0v2\0c\0"),
+            ),
+            (
+                vec![
+                    Some("c"),
+                    Some("v3"),
+                    Some("a"),
+                    None,
+                    Some("b"),
+                    Some("v2"),
+                ],
+                Bytes::from_static(b"a\0\0b\0v2\0c\0v3"),
+            ),
+            (
+                vec![
+                    Some("d"),
+                    None,
+                    Some("b"),
+                    Some("1"),
+                    Some("a"),
+                    None,
+                    Some("c"),
+                    Some("3"),
+                ],
+                Bytes::from_static(b"a\0\0b\x001\0c\x003\0d\0"),
+            ),
+        ];
+        for (arr, bytes) in table.iter() {
+            let arr_iter = arr.iter().map(|s| s.map(|s| s.to_string()));
+            let result = crate::checksum_bytes(arr_iter, NullPolicy::Keep);
+            assert_eq!(result, bytes, "using {:?}", arr);
+        }
+    }
+
+    #[test]
+    fn test_normalized_pairs() {
+        let table = [
+            (
+                vec!["a", "v", "b", "v2"],
+                Bytes::from_static(b"a\0v\0b\0v2"),
+            ),
+            (
+                vec!["b", "v2", "a", "v"],
+                Bytes::from_static(b"a\0v\0b\0v2"),
+            ),
+            (
+                vec!["c", "v3", "a", "v", "b", "v2"],
+                Bytes::from_static(b"a\0v\0b\0v2\0c\0v3"),
+            ),
+            (
+                vec!["b", "v2", "c", "v3", "a", "v"],
+                Bytes::from_static(b"a\0v\0b\0v2\0c\0v3"),
+            ),
+        ];
+        for (arr, bytes) in table.iter() {
+            let arr_iter = arr.iter().map(|s| s.to_string()).map(Option::Some);
+            let result = crate::checksum_bytes(arr_iter, NullPolicy::Skip);
+            assert_eq!(result, bytes, "using {:?}", arr);
+        }
+
+        let table = [
+            (
+                vec![Some("c"), Some("v3"), Some("a"), None, Some("b"), None],
+                Bytes::from_static(b"c\0v3"),
+            ),
+            (
+                vec![
+                    Some("c"),
+                    Some("v3"),
+                    Some("a"),
+                    None,
+                    Some("b"),
+                    Some("v2"),
+                ],
+                Bytes::from_static(b"b\0v2\0c\0v3"),
+            ),
+            (
+                vec![
+                    Some("d"),
+                    None,
+                    Some("b"),
+                    Some("1"),
+                    Some("a"),
+                    None,
+                    Some("c"),
+                    Some("3"),
+                ],
+                Bytes::from_static(b"b\x001\0c\x003"),
+            ),
+        ];
+        for (arr, bytes) in table.iter() {
+            let arr_iter = arr.iter().map(|s| s.map(|s| s.to_string()));
+            let result = crate::checksum_bytes(arr_iter, NullPolicy::Skip);
+            assert_eq!(result, bytes, "using {:?}", arr);
+        }
+    }
+
+    #[test]
+    fn test_repeated_keys() {
+        let pairs = [Some("b"), Some("2"), Some("a"), Some("1"), Some("b"), Some("1")];
+        let reordered = [Some("b"), Some("1"), Some("a"), Some("1"), Some("b"), Some("2")];
+        // encoding version 1 keeps few pairs with the same key in argument order
+        assert_eq!(
+            crate::checksum_bytes(pairs, NullPolicy::Keep),
+            Bytes::from_static(b"a\x001\0b\x002\0b\x001")
+        );
+        assert_eq!(
+            crate::checksum_bytes(reordered, NullPolicy::Keep),
+            Bytes::from_static(b"a\x001\0b\x001\0b\x002")
+        );
+        // encoding version 2 sorts them by value
+        for pairs in [pairs, reordered] {
+            assert_eq!(
+                crate::checksum_bytes_v2(pairs, NullPolicy::Keep),
+                Bytes::from_static(b"a\x001\0b\x001\0b\x002")
+            );
+        }
+        assert_eq!(
+            checksum_fingerprint_v2::<Seahash, _>(pairs, NullPolicy::Skip),
+            checksum_fingerprint_v2::<Seahash, _>(reordered, NullPolicy::Skip)
+        );
+    }
+
+    #[test]
+    fn test_pair_bytes() {
+        let pairs = [Some("b"), None, Some("a"), Some("1"), Some("a"), Some("1")];
+        assert_eq!(
+            crate::pair_bytes(pairs, NullPolicy::Keep),
+            [Bytes::from_static(b"a\x001"), Bytes::from_static(b"b\0")]
+        );
+        assert_eq!(
+            crate::pair_bytes(pairs, NullPolicy::Skip),
+            [Bytes::from_static(b"a\x001")]
+        );
+    }
+
+    #[test]
+    fn test_id_bytes() {
+        for (bytes, ids, _, _) in ID_GOLDEN_TABLE.iter() {
+            let result = crate::id_bytes(ids.iter().map(|s| s.to_string()));
+            assert_eq!(result, bytes, "using {:?}", ids);
+        }
+    }
+
+    #[test]
+    fn test_farmhash() {
+        for (_, ids, golden, _) in ID_GOLDEN_TABLE.iter() {
+            let result = id_fingerprint::<Farmhash, _>(ids.iter().map(|s| s.to_string()));
+            assert_eq!(result, *golden, "using {:?}", ids);
+        }
+    }
+
+    #[test]
+    fn test_seahash() {
+        for (_, ids, _, golden) in ID_GOLDEN_TABLE.iter() {
+            let result = id_fingerprint::<Seahash, _>(ids.iter().map(|s| s.to_string()));
+            assert_eq!(result, *golden, "using {:?}", ids);
+        }
+    }
+
+    #[test]
+    fn test_checksum() {
+        for (params, farm_ext, sea_ext, farm, sea) in CHECKSUM_GOLDEN_TABLE.iter() {
+            let pairs = || params.iter().map(|s| s.map(String::from));
+            assert_eq!(
+                checksum_fingerprint::<Farmhash, _>(pairs(), NullPolicy::Skip),
+                *farm_ext,
+                "using {:?}",
+                params
+            );
+            assert_eq!(
+                checksum_fingerprint::<Seahash, _>(pairs(), NullPolicy::Skip),
+                *sea_ext,
+                "using {:?}",
+                params
+            );
+            assert_eq!(
+                checksum_fingerprint::<Farmhash, _>(pairs(), NullPolicy::Keep),
+                *farm,
+                "using {:?}",
+                params
+            );
+            assert_eq!(
+                checksum_fingerprint::<Seahash, _>(pairs(), NullPolicy::Keep),
+                *sea,
+                "using {:?}",
+                params
+            );
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! Hash partitions as assigned by `PARTITION BY HASH` with the extension's hash operator classes.
+//!
+//! The hash of a value is
+//!
+//! * `farmhash_text_ops`: the low 64 bits of `id_farmhash(value)`,
+//! * `seahash_text_ops`: `id_seahash(value)`,
+//! * `farmhash_uuid_ops`: the low 64 bits of the `uuid`, i.e. of a stored `id_farmhash` or
+//!   `checksum_farmhash` fingerprint.
+//!
+//! ```
+//! use pg_row_hashes_fingerprint::partition::hash_partition;
+//! use pg_row_hashes_fingerprint::{id_fingerprint, Seahash};
+//!
+//! // CREATE TABLE t (name text) PARTITION BY HASH (name seahash_text_ops);
+//! // CREATE TABLE t_3 PARTITION OF t FOR VALUES WITH (MODULUS 8, REMAINDER 3);
+//! let remainder = hash_partition([Some(id_fingerprint::<Seahash, _>(["alice"]))], 8);
+//! assert!(remainder < 8);
+//! ```
+
+use crate::splitmix64;
+
+/// Seed Postgres passes to the extended hash function of every partition key column.
+pub const HASH_PARTITION_SEED: u64 = 0x7a5b_2236_7996_dcfd;
+
+/// Extended hash support function of the operator classes: the hash itself for seed 0, as
+/// Postgres requires, a mix of both otherwise.
+#[inline]
+pub fn hash_extended(hash: u64, seed: u64) -> u64 {
+    if seed == 0 {
+        return hash;
+    }
+    splitmix64(hash ^ seed)
+}
+
+/// Remainder of the hash partition of a row, given the hashes of its partition key columns in
+/// order (`None` for `NULL`).
+pub fn hash_partition(hashes: impl IntoIterator<Item = Option<u64>>, modulus: u64) -> u64 {
+    hashes.into_iter().flatten().fold(0, |row_hash, hash| {
+        hash_combine64(row_hash, hash_extended(hash, HASH_PARTITION_SEED))
+    }) % modulus
+}
+
+/// Postgres' `hash_combine64`.
+#[inline]
+fn hash_combine64(a: u64, b: u64) -> u64 {
+    a ^ b
+        .wrapping_add(0x49a0_f4dd_15e5_a8e3)
+        .wrapping_add(a << 54)
+        .wrapping_add(a >> 7)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hash_combine64, hash_extended, hash_partition, HASH_PARTITION_SEED};
+
+    #[test]
+    fn test_hash_extended() {
+        assert_eq!(hash_extended(42, 0), 42);
+        assert_ne!(hash_extended(42, 1), hash_extended(42, 2));
+        assert_ne!(hash_extended(42, 1), 42);
+    }
+
+    #[test]
+    fn test_hash_partition() {
+        assert_eq!(hash_partition([None, None], 4), 0);
+        let h = hash_extended(42, HASH_PARTITION_SEED);
+        assert_eq!(hash_partition([Some(42)], 4), hash_combine64(0, h) % 4);
+        assert_eq!(
+            hash_partition([Some(42), None, Some(7)], 5),
+            hash_partition([Some(42), Some(7)], 5)
+        );
+        assert_eq!(hash_combine64(0, 1), 0x49a0_f4dd_15e5_a8e4);
+    }
+}
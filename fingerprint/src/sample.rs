@@ -0,0 +1,46 @@
+//! Deterministic samples by fingerprint, like `fingerprint_sample` and the `fingerprint_sample`
+//! `TABLESAMPLE` method of the extension.
+//!
+//! ```
+//! use pg_row_hashes_fingerprint::sample::sampled;
+//! use pg_row_hashes_fingerprint::{id_fingerprint, Seahash};
+//!
+//! // SELECT fingerprint_sample(0.01, '42');
+//! let in_sample = sampled(id_fingerprint::<Seahash, _>(["42"]), 0.01);
+//! # let _ = in_sample;
+//! ```
+
+/// Whether `key` is in the sample of about `rate` (0 to 1) of all keys: whether the key, read as
+/// unsigned integer, is below `rate * 2^64`.
+///
+/// Unlike `abs(key) % 100` of the signed `bigint`, this is unbiased, and the sample of a smaller
+/// rate is always contained in the sample of a larger rate.
+#[inline]
+pub fn sampled(key: u64, rate: f64) -> bool {
+    if rate >= 1.0 {
+        return true;
+    }
+    // casting saturates, negative rates and NaN sample nothing
+    key < (rate * 18_446_744_073_709_551_616.0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sampled;
+    use crate::splitmix64;
+
+    #[test]
+    fn test_sampled() {
+        let keys = || (0..100_000).map(splitmix64);
+        assert_eq!(keys().filter(|&k| sampled(k, 0.0)).count(), 0);
+        assert_eq!(keys().filter(|&k| sampled(k, 1.0)).count(), 100_000);
+        assert!(sampled(u64::MAX, 1.0));
+        assert!(!sampled(0, f64::NAN));
+
+        let count = keys().filter(|&k| sampled(k, 0.01)).count();
+        assert!((800..1200).contains(&count), "{count} sampled");
+        assert!(keys()
+            .filter(|&k| sampled(k, 0.01))
+            .all(|k| sampled(k, 0.02)));
+    }
+}
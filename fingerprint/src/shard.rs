@@ -0,0 +1,121 @@
+//! Shard assignment of keys, like the `shard_for*` functions of the extension.
+//!
+//! The key is the fingerprint `id_seahash` of the ids, so routers can place rows exactly where the
+//! database does.
+//!
+//! ```
+//! use pg_row_hashes_fingerprint::shard::{jump, rendezvous};
+//! use pg_row_hashes_fingerprint::{id_fingerprint, Seahash};
+//!
+//! let key = id_fingerprint::<Seahash, _>(["customer", "42"]);
+//! // SELECT shard_for_jump(16, 'customer', '42');
+//! assert!(jump(key, 16) < 16);
+//! // SELECT shard_for_rendezvous('{db1,db2,db3}', 'customer', '42');
+//! assert!(rendezvous(key, ["db1", "db2", "db3"]).is_some());
+//! ```
+
+use crate::{splitmix64, Algorithm, Seahash};
+
+/// Shard of `key` among `shards` shards: `key % shards`. Changing the number of shards moves
+/// almost every key.
+#[inline]
+pub fn modulo(key: u64, shards: u32) -> u32 {
+    (key % u64::from(shards)) as u32
+}
+
+/// Jump consistent hash (Lamping, Veach: "A Fast, Minimal Memory, Consistent Hash Algorithm").
+///
+/// Going from `n` to `n + 1` shards only moves keys to the new shard `n`, about `1 / (n + 1)` of
+/// them. Shards can only be added or removed at the end.
+pub fn jump(key: u64, shards: u32) -> u32 {
+    let mut key = key;
+    let mut shard: i64 = -1;
+    let mut next: i64 = 0;
+    while next < i64::from(shards) {
+        shard = next;
+        key = key.wrapping_mul(2_862_933_555_777_941_757).wrapping_add(1);
+        next = ((shard + 1) as f64 * ((1u64 << 31) as f64 / ((key >> 33) + 1) as f64)) as i64;
+    }
+    shard as u32
+}
+
+/// Rendezvous (highest random weight) hashing: the shard with the highest weight
+/// `splitmix64(key ^ seahash(shard))`, `None` if there are no shards.
+///
+/// Removing any shard only moves the keys of that shard, adding one only moves keys to it.
+pub fn rendezvous<T: AsRef<[u8]>>(key: u64, shards: impl IntoIterator<Item = T>) -> Option<T> {
+    shards
+        .into_iter()
+        .map(|shard| {
+            (
+                splitmix64(key ^ Seahash::fingerprint(shard.as_ref())),
+                shard,
+            )
+        })
+        .max_by_key(|(weight, _)| *weight)
+        .map(|(_, shard)| shard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{jump, modulo, rendezvous};
+    use crate::splitmix64;
+
+    fn keys() -> impl Iterator<Item = u64> {
+        (0..10_000).map(splitmix64)
+    }
+
+    #[test]
+    fn test_modulo() {
+        assert_eq!(modulo(10, 4), 2);
+        assert_eq!(modulo(u64::MAX, 7), (u64::MAX % 7) as u32);
+    }
+
+    #[test]
+    fn test_jump_moves_keys_to_new_shard_only() {
+        for key in keys() {
+            assert_eq!(jump(key, 1), 0);
+            for shards in 1..20 {
+                let (before, after) = (jump(key, shards), jump(key, shards + 1));
+                assert!(after < shards + 1);
+                assert!(
+                    after == before || after == shards,
+                    "{key} moved from {before} to {after}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_jump_distribution() {
+        let mut counts = [0; 10];
+        for key in keys() {
+            counts[jump(key, 10) as usize] += 1;
+        }
+        assert!(
+            counts.iter().all(|&c| (800..1200).contains(&c)),
+            "{counts:?}"
+        );
+    }
+
+    #[test]
+    fn test_rendezvous_moves_keys_of_removed_shard_only() {
+        let shards = ["a", "b", "c", "d", "e"];
+        let without_c = ["a", "b", "d", "e"];
+        let mut counts = [0; 5];
+        for key in keys() {
+            let before = rendezvous(key, shards).expect("there are shards");
+            let after = rendezvous(key, without_c).expect("there are shards");
+            assert!(
+                before == "c" || before == after,
+                "{key} moved from {before} to {after}"
+            );
+            counts[shards.iter().position(|&s| s == before).expect("a shard")] += 1;
+        }
+        assert!(
+            counts.iter().all(|&c| (1700..2300).contains(&c)),
+            "{counts:?}"
+        );
+        assert_eq!(rendezvous(1, Vec::<&str>::new()), None);
+    }
+}
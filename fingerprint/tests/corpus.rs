@@ -0,0 +1,338 @@
+//! Fingerprints of a large corpus of inputs, to keep them stable across releases.
+//!
+//! The corpus in `tests/corpus` is in the format of `COPY ... (FORMAT text)`, so that the
+//! extension's tests can load it as is:
+//!
+//! * `ids.tsv`: `ids text[]`, `id_farmhash uuid`, `id_seahash bigint`,
+//! * `checksums.tsv`: `pairs text[]`, `checksum_farmhash uuid`, `checksum_seahash bigint`,
+//!   `checksum_farmhash_extendable uuid`, `checksum_seahash_extendable bigint`.
+//!
+//! It is generated by `cargo test --test corpus -- --ignored generate_corpus`. Fingerprints are
+//! persisted by users, so a regenerated corpus may only ever add lines. The cases with more than 20
+//! pairs were also checked against the encoder of the released extension, as only they let its
+//! unstable sort reorder pairs with the same key.
+
+use std::fs;
+use std::path::PathBuf;
+
+use pg_row_hashes_fingerprint::{
+    checksum_fingerprint, id_fingerprint, splitmix64, Farmhash, NullPolicy, Seahash,
+};
+
+const ID_CASES: usize = 2000;
+const CHECKSUM_CASES: usize = 3000;
+const LONG_CHECKSUM_CASES: usize = 500;
+
+fn corpus_path(name: &str) -> PathBuf {
+    [env!("CARGO_MANIFEST_DIR"), "tests", "corpus", name]
+        .iter()
+        .collect()
+}
+
+/// Deterministic pseudo random numbers (splitmix64), the corpus must not depend on a rand crate.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        let state = self.0;
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        splitmix64(state)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+
+    fn chars(&mut self, alphabet: &[char], len: usize) -> String {
+        (0..len).map(|_| *self.pick(alphabet)).collect()
+    }
+}
+
+/// A text as found in a row: empty, ASCII, numbers, unicode or characters with a special
+/// meaning in COPY and array literals. Never contains `\0`, just as Postgres' `text`.
+fn text(rng: &mut Rng) -> String {
+    const SPECIAL: &[&str] = &[
+        "NULL", "null", "\\N", "\\", "\"", "'", ",", "{", "}", "{}", "{a,b}", " ", "  a ", "\t",
+        "\n", "\r\n", "a\\0b", "\u{1}", "\u{7f}",
+    ];
+    const UNICODE: &str = "äöüßéñ€😀👍\u{1f3fd}中文عשΩ\u{301}\u{200b}\u{feff}\u{fffd}\u{10ffff}";
+    let unicode: Vec<char> = UNICODE.chars().collect();
+    let ascii: Vec<char> = (' '..='~').collect();
+    let alnum: Vec<char> = ('a'..='z').chain('A'..='Z').chain('0'..='9').collect();
+
+    match rng.below(10) {
+        0 => String::new(),
+        1 => rng.pick(SPECIAL).to_string(),
+        2..=4 => {
+            let len = 1 + rng.below(12);
+            rng.chars(&alnum, len)
+        }
+        5 => {
+            let len = 1 + rng.below(6);
+            rng.chars(&unicode, len)
+        }
+        6 => {
+            let len = 1 + rng.below(20);
+            rng.chars(&ascii, len)
+        }
+        7 => rng
+            .pick(&[
+                "0", "-1", "42", "0042", "1.5", "-12.50", "1e10", "NaN", "Infinity",
+            ])
+            .to_string(),
+        8 => {
+            let len = 50 + rng.below(250);
+            rng.chars(&ascii, len)
+        }
+        _ => {
+            // similar short texts, to catch encodings that only differ in separators
+            let len = 1 + rng.below(3);
+            rng.chars(&['a', 'b'], len)
+        }
+    }
+}
+
+fn ids(rng: &mut Rng) -> Vec<Option<String>> {
+    let len = 1 + rng.below(8);
+    (0..len).map(|_| Some(text(rng))).collect()
+}
+
+/// Alternating keys and values with `NULL` values, `NULL` keys and repeated keys.
+fn pairs(rng: &mut Rng) -> Vec<Option<String>> {
+    let len = 1 + rng.below(8);
+    let mut pairs: Vec<Option<String>> = Vec::with_capacity(2 * len);
+    for _ in 0..len {
+        let key = match rng.below(20) {
+            0 => None,
+            1 | 2 if !pairs.is_empty() => {
+                let i = rng.below(pairs.len() / 2);
+                pairs[2 * i].clone()
+            }
+            _ => Some(text(rng)),
+        };
+        let value = (rng.below(5) != 0).then(|| text(rng));
+        pairs.extend([key, value]);
+    }
+    pairs
+}
+
+/// More than 20 pairs drawn from a few keys, as encoding version 1 only keeps up to 20 pairs with
+/// the same key in argument order.
+fn long_pairs(rng: &mut Rng) -> Vec<Option<String>> {
+    let keys: Vec<Option<String>> = (0..1 + rng.below(8))
+        .map(|_| (rng.below(20) != 0).then(|| text(rng)))
+        .collect();
+    let len = 21 + rng.below(60);
+    let mut pairs = Vec::with_capacity(2 * len);
+    for _ in 0..len {
+        let key = rng.pick(&keys).clone();
+        let value = (rng.below(5) != 0).then(|| text(rng));
+        pairs.extend([key, value]);
+    }
+    pairs
+}
+
+/// Array literal as accepted by `text[]`.
+fn array_literal(values: &[Option<String>]) -> String {
+    let elements: Vec<String> = values
+        .iter()
+        .map(|value| match value {
+            None => "NULL".to_string(),
+            Some(value) => format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"")),
+        })
+        .collect();
+    format!("{{{}}}", elements.join(","))
+}
+
+fn parse_array_literal(literal: &str) -> Vec<Option<String>> {
+    let inner = literal
+        .strip_prefix('{')
+        .and_then(|l| l.strip_suffix('}'))
+        .unwrap_or_else(|| panic!("not an array literal: {literal}"));
+    let mut values = Vec::new();
+    let mut chars = inner.chars().peekable();
+    while chars.peek().is_some() {
+        if chars.next_if_eq(&'"').is_some() {
+            let mut value = String::new();
+            loop {
+                match chars.next().expect("unterminated element") {
+                    '"' => break,
+                    '\\' => value.push(chars.next().expect("unterminated escape")),
+                    c => value.push(c),
+                }
+            }
+            values.push(Some(value));
+        } else {
+            let element: String = chars.by_ref().take_while(|&c| c != ',').collect();
+            assert_eq!(element, "NULL", "unquoted element in {literal}");
+            values.push(None);
+            continue;
+        }
+        chars.next_if_eq(&',');
+    }
+    values
+}
+
+/// Field as written by `COPY ... (FORMAT text)`.
+fn copy_escape(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn copy_unescape(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        unescaped.push(match c {
+            '\\' => match chars.next() {
+                Some('t') => '\t',
+                Some('n') => '\n',
+                Some('r') => '\r',
+                Some('\\') => '\\',
+                other => panic!("unexpected escape {other:?} in {field}"),
+            },
+            c => c,
+        });
+    }
+    unescaped
+}
+
+/// `uuid` as returned by the `*_farmhash` functions.
+fn uuid(digest: u128) -> String {
+    let hex: String = digest
+        .to_le_bytes()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// `bigint` as returned by the `*_seahash` functions.
+fn bigint(digest: u64) -> String {
+    (digest as i64).to_string()
+}
+
+fn id_fingerprints(ids: &[Option<String>]) -> Vec<String> {
+    let ids = || {
+        ids.iter()
+            .map(|id| id.as_deref().expect("ids are not NULL"))
+    };
+    vec![
+        uuid(id_fingerprint::<Farmhash, _>(ids())),
+        bigint(id_fingerprint::<Seahash, _>(ids())),
+    ]
+}
+
+fn checksum_fingerprints(pairs: &[Option<String>]) -> Vec<String> {
+    let pairs = || pairs.iter().map(Option::as_deref);
+    vec![
+        uuid(checksum_fingerprint::<Farmhash, _>(
+            pairs(),
+            NullPolicy::Keep,
+        )),
+        bigint(checksum_fingerprint::<Seahash, _>(
+            pairs(),
+            NullPolicy::Keep,
+        )),
+        uuid(checksum_fingerprint::<Farmhash, _>(
+            pairs(),
+            NullPolicy::Skip,
+        )),
+        bigint(checksum_fingerprint::<Seahash, _>(
+            pairs(),
+            NullPolicy::Skip,
+        )),
+    ]
+}
+
+/// Cases generated by `input` from a seeded [`Rng`].
+type Cases = (u64, usize, fn(&mut Rng) -> Vec<Option<String>>);
+
+/// Write the cases of every part in turn, new parts are only ever appended.
+fn write_corpus(name: &str, parts: &[Cases], fingerprints: fn(&[Option<String>]) -> Vec<String>) {
+    let mut lines = String::new();
+    for &(seed, cases, input) in parts {
+        let mut rng = Rng(seed);
+        for _ in 0..cases {
+            let values = input(&mut rng);
+            let mut fields = vec![copy_escape(&array_literal(&values))];
+            fields.extend(fingerprints(&values));
+            lines += &(fields.join("\t") + "\n");
+        }
+    }
+    fs::write(corpus_path(name), lines).expect("can't write corpus");
+}
+
+fn check_corpus(name: &str, cases: usize, fingerprints: fn(&[Option<String>]) -> Vec<String>) {
+    let corpus = fs::read_to_string(corpus_path(name)).expect("can't read corpus");
+    let mut count = 0;
+    for (line, fields) in corpus.lines().map(|l| l.split('\t')).enumerate() {
+        let fields: Vec<&str> = fields.collect();
+        let values = parse_array_literal(&copy_unescape(fields[0]));
+        assert_eq!(
+            fingerprints(&values),
+            fields[1..],
+            "in {name}:{} using {values:?}",
+            line + 1
+        );
+        count += 1;
+    }
+    assert!(count >= cases, "only {count} cases in {name}");
+}
+
+#[test]
+#[ignore]
+fn generate_corpus() {
+    write_corpus("ids.tsv", &[(1, ID_CASES, ids)], id_fingerprints);
+    write_corpus(
+        "checksums.tsv",
+        &[
+            (2, CHECKSUM_CASES, pairs),
+            (3, LONG_CHECKSUM_CASES, long_pairs),
+        ],
+        checksum_fingerprints,
+    );
+}
+
+#[test]
+fn test_id_corpus() {
+    check_corpus("ids.tsv", ID_CASES, id_fingerprints);
+}
+
+#[test]
+fn test_checksum_corpus() {
+    check_corpus(
+        "checksums.tsv",
+        CHECKSUM_CASES + LONG_CHECKSUM_CASES,
+        checksum_fingerprints,
+    );
+}
+
+#[test]
+fn test_array_literal() {
+    let values = vec![
+        Some("a".to_string()),
+        None,
+        Some("NULL".to_string()),
+        Some("\"{,}\\".to_string()),
+        Some(String::new()),
+    ];
+    let literal = array_literal(&values);
+    assert_eq!(literal, r#"{"a",NULL,"NULL","\"{,}\\",""}"#);
+    assert_eq!(parse_array_literal(&literal), values);
+    assert_eq!(copy_unescape(&copy_escape("a\\b\tc\nd\r")), "a\\b\tc\nd\r");
+}
@@ -0,0 +1,210 @@
+//! Invariants of the canonical encoding promised by the README, checked on generated input.
+//!
+//! Generated texts never contain a `\0` byte, as Postgres' `text` can't either. Keys are unique
+//! unless a pair is repeated as a whole, except for encoding version 2: only it canonicalizes the
+//! order of pairs with the same key but different values.
+
+use std::collections::BTreeMap;
+
+use bytes::Bytes;
+use pg_row_hashes_fingerprint::{
+    checksum_bytes, checksum_bytes_v2, checksum_fingerprint, checksum_fingerprint_v2, id_bytes,
+    id_fingerprint, Farmhash, NullPolicy, Seahash,
+};
+use proptest::prelude::*;
+use proptest::sample::Index;
+
+/// Texts without `\0`, often from a tiny alphabet so that distinct inputs are similar.
+fn text() -> impl Strategy<Value = String> {
+    prop_oneof!["[ab]{0,2}", "[^\\x00]{0,8}"]
+}
+
+fn row() -> impl Strategy<Value = BTreeMap<String, Option<String>>> {
+    prop::collection::btree_map(text(), prop::option::of(text()), 0..8)
+}
+
+/// Alternating keys and values, as passed to the SQL functions.
+fn arguments<'a>(
+    pairs: impl IntoIterator<Item = (&'a String, &'a Option<String>)>,
+) -> Vec<Option<&'a str>> {
+    pairs
+        .into_iter()
+        .flat_map(|(key, value)| [Some(key.as_str()), value.as_deref()])
+        .collect()
+}
+
+/// Ids from the encoding, the inverse of `id_bytes` for at least one id.
+fn decode_ids(bytes: &[u8]) -> Vec<&[u8]> {
+    bytes.split(|&b| b == 0).collect()
+}
+
+/// Key, value pairs from the encoding, the inverse of `checksum_bytes` with `NULL` as empty value.
+fn decode_pairs(bytes: &[u8]) -> Vec<(&[u8], &[u8])> {
+    if bytes.is_empty() {
+        return Vec::new();
+    }
+    let parts: Vec<&[u8]> = bytes.split(|&b| b == 0).collect();
+    assert_eq!(parts.len() % 2, 0, "odd number of parts in {bytes:?}");
+    parts.chunks(2).map(|pair| (pair[0], pair[1])).collect()
+}
+
+/// The row as it is encoded: `NULL` values skipped or, indistinguishable from an empty
+/// string, kept as one.
+fn normalized(row: &BTreeMap<String, Option<String>>, nulls: NullPolicy) -> BTreeMap<&str, &str> {
+    row.iter()
+        .filter(|(_, value)| nulls == NullPolicy::Keep || value.is_some())
+        .map(|(key, value)| (key.as_str(), value.as_deref().unwrap_or("")))
+        .collect()
+}
+
+proptest! {
+    #[test]
+    fn checksum_is_permutation_invariant(
+        (pairs, shuffled) in row()
+            .prop_flat_map(|row| (Just(row), prop::collection::vec(any::<Index>(), 0..4)))
+            .prop_flat_map(|(row, repeated)| {
+                // repeating a pair as a whole doesn't change the checksum either
+                let mut pairs: Vec<_> = row.into_iter().collect();
+                if !pairs.is_empty() {
+                    let copies: Vec<_> = repeated.iter().map(|i| i.get(&pairs).clone()).collect();
+                    pairs.extend(copies);
+                }
+                (Just(pairs.clone()), Just(pairs).prop_shuffle())
+            })
+    ) {
+        let original = arguments(pairs.iter().map(|(k, v)| (k, v)));
+        let shuffled = arguments(shuffled.iter().map(|(k, v)| (k, v)));
+        for nulls in [NullPolicy::Keep, NullPolicy::Skip] {
+            prop_assert_eq!(
+                checksum_bytes(original.iter().copied(), nulls),
+                checksum_bytes(shuffled.iter().copied(), nulls)
+            );
+            prop_assert_eq!(
+                checksum_fingerprint::<Farmhash, _>(original.iter().copied(), nulls),
+                checksum_fingerprint::<Farmhash, _>(shuffled.iter().copied(), nulls)
+            );
+            prop_assert_eq!(
+                checksum_fingerprint::<Seahash, _>(original.iter().copied(), nulls),
+                checksum_fingerprint::<Seahash, _>(shuffled.iter().copied(), nulls)
+            );
+        }
+    }
+
+    #[test]
+    fn checksum_v2_is_permutation_invariant(
+        (pairs, shuffled) in prop::collection::vec(("[ab]{0,1}", prop::option::of(text())), 0..8)
+            .prop_flat_map(|pairs| (Just(pairs.clone()), Just(pairs).prop_shuffle()))
+    ) {
+        // keys from a tiny alphabet, so that most rows repeat keys with different values
+        let original = arguments(pairs.iter().map(|(k, v)| (k, v)));
+        let shuffled = arguments(shuffled.iter().map(|(k, v)| (k, v)));
+        for nulls in [NullPolicy::Keep, NullPolicy::Skip] {
+            prop_assert_eq!(
+                checksum_bytes_v2(original.iter().copied(), nulls),
+                checksum_bytes_v2(shuffled.iter().copied(), nulls)
+            );
+            prop_assert_eq!(
+                checksum_fingerprint_v2::<Farmhash, _>(original.iter().copied(), nulls),
+                checksum_fingerprint_v2::<Farmhash, _>(shuffled.iter().copied(), nulls)
+            );
+            prop_assert_eq!(
+                checksum_fingerprint_v2::<Seahash, _>(original.iter().copied(), nulls),
+                checksum_fingerprint_v2::<Seahash, _>(shuffled.iter().copied(), nulls)
+            );
+        }
+    }
+
+    #[test]
+    fn extendable_checksum_equals_non_null_subset(row in row()) {
+        let all = arguments(&row);
+        let non_null = arguments(row.iter().filter(|(_, value)| value.is_some()));
+
+        let extendable = checksum_bytes(all.iter().copied(), NullPolicy::Skip);
+        prop_assert_eq!(&extendable, &checksum_bytes(non_null.iter().copied(), NullPolicy::Skip));
+        prop_assert_eq!(&extendable, &checksum_bytes(non_null.iter().copied(), NullPolicy::Keep));
+        prop_assert_eq!(
+            checksum_fingerprint::<Farmhash, _>(all.iter().copied(), NullPolicy::Skip),
+            checksum_fingerprint::<Farmhash, _>(non_null.iter().copied(), NullPolicy::Keep)
+        );
+        prop_assert_eq!(
+            checksum_fingerprint::<Seahash, _>(all.iter().copied(), NullPolicy::Skip),
+            checksum_fingerprint::<Seahash, _>(non_null.iter().copied(), NullPolicy::Keep)
+        );
+    }
+
+    #[test]
+    fn id_is_order_sensitive(
+        (ids, shuffled) in prop::collection::vec(text(), 1..6)
+            .prop_flat_map(|ids| (Just(ids.clone()), Just(ids).prop_shuffle()))
+    ) {
+        prop_assume!(ids != shuffled);
+        prop_assert_ne!(id_bytes(&ids), id_bytes(&shuffled));
+        prop_assert_ne!(
+            id_fingerprint::<Farmhash, _>(&ids),
+            id_fingerprint::<Farmhash, _>(&shuffled)
+        );
+        prop_assert_ne!(
+            id_fingerprint::<Seahash, _>(&ids),
+            id_fingerprint::<Seahash, _>(&shuffled)
+        );
+    }
+
+    #[test]
+    fn distinct_ids_have_distinct_encodings(
+        a in prop::collection::vec(text(), 1..4),
+        b in prop::collection::vec(text(), 1..4),
+    ) {
+        let encoded = id_bytes(&a);
+        let decoded: Vec<&[u8]> = a.iter().map(String::as_bytes).collect();
+        prop_assert_eq!(decode_ids(&encoded), decoded);
+        prop_assert_eq!(encoded == id_bytes(&b), a == b);
+    }
+
+    #[test]
+    fn distinct_rows_have_distinct_encodings(a in row(), b in row()) {
+        for nulls in [NullPolicy::Keep, NullPolicy::Skip] {
+            let encoded = checksum_bytes(arguments(&a), nulls);
+            let decoded: Vec<(&[u8], &[u8])> = normalized(&a, nulls)
+                .into_iter()
+                .map(|(key, value)| (key.as_bytes(), value.as_bytes()))
+                .collect();
+            prop_assert_eq!(decode_pairs(&encoded), decoded);
+            prop_assert_eq!(
+                encoded == checksum_bytes(arguments(&b), nulls),
+                normalized(&a, nulls) == normalized(&b, nulls)
+            );
+        }
+    }
+}
+
+#[test]
+fn null_and_empty_values_share_an_encoding() {
+    // documents the one known collision: `NULL` is kept as an empty value
+    assert_eq!(
+        checksum_bytes([Some("a"), None], NullPolicy::Keep),
+        checksum_bytes([Some("a"), Some("")], NullPolicy::Keep)
+    );
+    assert_eq!(
+        checksum_bytes([Some("a"), Some("")], NullPolicy::Keep),
+        Bytes::from_static(b"a\0")
+    );
+}
+
+#[test]
+fn repeated_keys_keep_their_order_in_version_1() {
+    // documents why there is an encoding version 2: the order of pairs with the same key counts
+    let pairs = [Some("a"), Some("1"), Some("a"), Some("2")];
+    let reordered = [Some("a"), Some("2"), Some("a"), Some("1")];
+    assert_ne!(
+        checksum_bytes(pairs, NullPolicy::Keep),
+        checksum_bytes(reordered, NullPolicy::Keep)
+    );
+    assert_eq!(
+        checksum_bytes_v2(pairs, NullPolicy::Keep),
+        checksum_bytes_v2(reordered, NullPolicy::Keep)
+    );
+    assert_eq!(
+        checksum_bytes_v2(reordered, NullPolicy::Keep),
+        Bytes::from_static(b"a\x001\0a\x002")
+    );
+}
@@ -0,0 +1,96 @@
+//! SQL benchmark of the fingerprint functions over generated tables, ignored by default:
+//! `cargo pgrx test pg18 bench_sql_functions -- --ignored`.
+//!
+//! Rows per second per function and table width are written to the server log.
+
+use pgrx::prelude::*;
+
+#[pg_schema]
+mod tests {
+    use std::time::Instant;
+
+    use pgrx::prelude::*;
+
+    const ROWS: i64 = 100_000;
+    const WIDTHS: [usize; 3] = [1, 4, 16];
+
+    /// SQL expressions over the columns `c1`..`cN` of a table of width `width`.
+    fn expressions(width: usize) -> Vec<(&'static str, String)> {
+        let columns: Vec<String> = (1..=width).map(|i| format!("c{i}")).collect();
+        let ids = columns.join(", ");
+        let pairs = columns
+            .iter()
+            .map(|c| format!("'{c}', {c}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        vec![
+            ("id_farmhash", format!("id_farmhash({ids})")),
+            ("id_seahash", format!("id_seahash({ids})")),
+            ("id_hash", format!("id_hash({ids})")),
+            ("checksum_farmhash", format!("checksum_farmhash({pairs})")),
+            (
+                "checksum_farmhash_extendable",
+                format!("checksum_farmhash_extendable({pairs})"),
+            ),
+            ("checksum_seahash", format!("checksum_seahash({pairs})")),
+            (
+                "checksum_seahash_extendable",
+                format!("checksum_seahash_extendable({pairs})"),
+            ),
+            ("checksum_hash", format!("checksum_hash({pairs})")),
+            (
+                "checksum_farmhash_record",
+                "checksum_farmhash_record(t)".to_string(),
+            ),
+            (
+                "checksum_seahash_record",
+                "checksum_seahash_record(t)".to_string(),
+            ),
+            (
+                "stream_seahash",
+                format!("stream_seahash({})", columns.join(" || ")),
+            ),
+        ]
+    }
+
+    #[pg_test]
+    #[ignore]
+    fn bench_sql_functions() {
+        for width in WIDTHS {
+            let columns = (1..=width)
+                .map(|i| format!("md5((i * {i})::text) AS c{i}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            Spi::run(&format!(
+                "CREATE TABLE bench_{width} AS SELECT {columns} FROM generate_series(1, {ROWS}) i;
+                 ANALYZE bench_{width};"
+            ))
+            .expect("SPI failed");
+
+            // the cost of reading the table, subtracted from every function
+            let baseline = time(&format!("SELECT count(*) FROM bench_{width} t"));
+
+            for (function, expression) in expressions(width) {
+                let elapsed = time(&format!("SELECT count({expression}) FROM bench_{width} t"));
+                let seconds = (elapsed - baseline).max(1e-9);
+                log!(
+                    "bench {function} width {width}: {:.0} rows/s",
+                    ROWS as f64 / seconds
+                );
+            }
+        }
+    }
+
+    /// Seconds to run `query`, the fastest of three runs.
+    fn time(query: &str) -> f64 {
+        (0..3)
+            .map(|_| {
+                let start = Instant::now();
+                let count = Spi::get_one::<i64>(query).expect("SPI failed");
+                assert_eq!(count, Some(ROWS), "using {query}");
+                start.elapsed().as_secs_f64()
+            })
+            .fold(f64::INFINITY, f64::min)
+    }
+}
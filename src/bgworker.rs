@@ -0,0 +1,166 @@
+use std::ffi::CString;
+use std::time::Duration;
+
+use pgrx::bgworkers::{BackgroundWorker, BackgroundWorkerBuilder, SignalWakeFlags};
+use pgrx::pg_sys::panic::CaughtError;
+use pgrx::prelude::*;
+use pgrx::{GucContext, GucFlags, GucRegistry, GucSetting};
+
+static ENABLED: GucSetting<bool> = GucSetting::<bool>::new(false);
+static DATABASE: GucSetting<Option<CString>> = GucSetting::<Option<CString>>::new(None);
+static RELATIONS: GucSetting<Option<CString>> = GucSetting::<Option<CString>>::new(None);
+static INTERVAL: GucSetting<i32> = GucSetting::<i32>::new(3600);
+
+/// Define the GUCs of the fingerprint worker and register it when loaded via
+/// `shared_preload_libraries`.
+///
+/// `pg_row_hashes.bgw_database` is a postmaster setting, which can only be defined while
+/// preloading, so none of them are defined otherwise.
+pub(crate) fn init() {
+    if unsafe { !pg_sys::process_shared_preload_libraries_in_progress } {
+        return;
+    }
+
+    GucRegistry::define_bool_guc(
+        c"pg_row_hashes.bgw_enabled",
+        c"Enables the fingerprint background worker.",
+        c"Periodically calls row_hashes.refresh() for every relation in pg_row_hashes.bgw_relations.",
+        &ENABLED,
+        GucContext::Sighup,
+        GucFlags::default(),
+    );
+    GucRegistry::define_string_guc(
+        c"pg_row_hashes.bgw_database",
+        c"Database the fingerprint background worker connects to.",
+        c"The extension has to be installed in this database.",
+        &DATABASE,
+        GucContext::Postmaster,
+        GucFlags::default(),
+    );
+    GucRegistry::define_string_guc(
+        c"pg_row_hashes.bgw_relations",
+        c"Comma separated list of relations fingerprinted by the background worker.",
+        c"",
+        &RELATIONS,
+        GucContext::Sighup,
+        GucFlags::LIST_INPUT,
+    );
+    GucRegistry::define_int_guc(
+        c"pg_row_hashes.bgw_interval",
+        c"Seconds between two runs of the fingerprint background worker.",
+        c"",
+        &INTERVAL,
+        1,
+        i32::MAX,
+        GucContext::Sighup,
+        GucFlags::UNIT_S,
+    );
+
+    BackgroundWorkerBuilder::new("pg_row_hashes fingerprint worker")
+        .set_type("pg_row_hashes")
+        .set_function("row_hashes_bgworker_main")
+        .set_library("pg_row_hashes")
+        .enable_spi_access()
+        .set_restart_time(Some(Duration::from_secs(60)))
+        .load();
+}
+
+#[pg_guard]
+#[unsafe(no_mangle)]
+pub extern "C-unwind" fn row_hashes_bgworker_main(_arg: pg_sys::Datum) {
+    BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+
+    let Some(database) = DATABASE.get() else {
+        log!("pg_row_hashes.bgw_database is not set, fingerprint worker exiting");
+        return;
+    };
+    BackgroundWorker::connect_worker_to_spi(Some(&database.to_string_lossy()), None);
+
+    loop {
+        if ENABLED.get() {
+            for relation in relations() {
+                BackgroundWorker::transaction(|| refresh_in_subtransaction(&relation));
+            }
+        }
+
+        let interval = Duration::from_secs(INTERVAL.get() as u64);
+        if !BackgroundWorker::wait_latch(Some(interval)) {
+            break;
+        }
+        if BackgroundWorker::sighup_received() {
+            unsafe { pg_sys::ProcessConfigFile(pg_sys::GucContext::PGC_SIGHUP) };
+        }
+    }
+}
+
+fn relations() -> Vec<String> {
+    RELATIONS
+        .get()
+        .map(|r| {
+            r.to_string_lossy()
+                .split(',')
+                .map(str::trim)
+                .filter(|r| !r.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// [`refresh`] in a subtransaction, so that an ERROR, e.g. because the relation was dropped or a
+/// lock timed out, only rolls back this relation's refresh and is logged as a warning instead of
+/// ending the worker and skipping the remaining relations.
+fn refresh_in_subtransaction(relation: &str) {
+    let (context, owner) = unsafe { (pg_sys::CurrentMemoryContext, pg_sys::CurrentResourceOwner) };
+    unsafe { pg_sys::BeginInternalSubTransaction(std::ptr::null()) };
+    PgTryBuilder::new(|| {
+        refresh(relation);
+        unsafe {
+            pg_sys::ReleaseCurrentSubTransaction();
+            pg_sys::MemoryContextSwitchTo(context);
+            pg_sys::CurrentResourceOwner = owner;
+        }
+    })
+    .catch_others(|e| {
+        unsafe {
+            pg_sys::MemoryContextSwitchTo(context);
+            pg_sys::RollbackAndReleaseCurrentSubTransaction();
+            pg_sys::CurrentResourceOwner = owner;
+        }
+        let message = match &e {
+            CaughtError::PostgresError(report) | CaughtError::ErrorReport(report) => {
+                report.message()
+            }
+            CaughtError::RustPanic { ereport, .. } => ereport.message(),
+        };
+        warning!("pg_row_hashes: could not fingerprint {relation}: {message}");
+    })
+    .execute()
+}
+
+fn refresh(relation: &str) {
+    let mismatches = Spi::connect_mut(|client| {
+        let relid = client
+            .select("SELECT to_regclass($1)::oid", None, &[relation.into()])?
+            .first()
+            .get_one::<pg_sys::Oid>()?;
+        let Some(relid) = relid else {
+            return Ok(None);
+        };
+        client
+            .update(
+                "SELECT row_hashes.refresh($1::regclass)",
+                None,
+                &[relid.into()],
+            )?
+            .first()
+            .get_one::<i64>()
+    });
+
+    match mismatches {
+        Ok(Some(0)) => {}
+        Ok(Some(n)) => warning!("pg_row_hashes: {n} fingerprint mismatches in {relation}"),
+        Ok(None) => warning!("pg_row_hashes: relation {relation} does not exist"),
+        Err(e) => warning!("pg_row_hashes: could not fingerprint {relation}: {e}"),
+    }
+}
@@ -0,0 +1,476 @@
+//! Input of `COPY ... TO ... (FORMAT binary)` files.
+//!
+//! Values are rendered like the type's output function with default settings (`DateStyle` ISO,
+//! `bytea_output` hex, `extra_float_digits` 1), `timestamptz` like with `TimeZone` UTC.
+
+use std::error::Error;
+use std::io::{BufReader, Read};
+
+use crate::Row;
+
+const SIGNATURE: &[u8; 11] = b"PGCOPY\n\xff\r\n\0";
+
+/// Days between 1970-01-01 and 2000-01-01, PostgreSQL's epoch.
+const POSTGRES_EPOCH_DAYS: i64 = 10957;
+const USECS_PER_DAY: i64 = 86_400_000_000;
+
+/// Types of the columns in the column spec.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum PgType {
+    Bool,
+    Int2,
+    Int4,
+    Int8,
+    Oid,
+    Float4,
+    Float8,
+    Numeric,
+    Text,
+    Jsonb,
+    Uuid,
+    Bytea,
+    Date,
+    Time,
+    Timestamp,
+    Timestamptz,
+}
+
+impl PgType {
+    fn parse(name: &str) -> Result<Self, Box<dyn Error>> {
+        // ignore type modifiers, e.g. numeric(10, 2) or varchar(20)
+        let name = name.split('(').next().unwrap_or_default().trim();
+        Ok(match name.to_ascii_lowercase().as_str() {
+            "bool" | "boolean" => PgType::Bool,
+            "int2" | "smallint" => PgType::Int2,
+            "int4" | "int" | "integer" => PgType::Int4,
+            "int8" | "bigint" => PgType::Int8,
+            "oid" => PgType::Oid,
+            "float4" | "real" => PgType::Float4,
+            "float8" | "double precision" => PgType::Float8,
+            "numeric" | "decimal" => PgType::Numeric,
+            "text" | "varchar" | "character varying" | "bpchar" | "char" | "character" | "name"
+            | "json" | "xml" => PgType::Text,
+            "jsonb" => PgType::Jsonb,
+            "uuid" => PgType::Uuid,
+            "bytea" => PgType::Bytea,
+            "date" => PgType::Date,
+            "time" | "time without time zone" => PgType::Time,
+            "timestamp" | "timestamp without time zone" => PgType::Timestamp,
+            "timestamptz" | "timestamp with time zone" => PgType::Timestamptz,
+            _ => return Err(format!("unsupported column type {name}").into()),
+        })
+    }
+
+    /// Render the binary representation as text.
+    fn render(self, value: &[u8]) -> Result<String, Box<dyn Error>> {
+        Ok(match self {
+            PgType::Bool => if read::<1>(value)?[0] != 0 { "t" } else { "f" }.to_string(),
+            PgType::Int2 => i16::from_be_bytes(read(value)?).to_string(),
+            PgType::Int4 => i32::from_be_bytes(read(value)?).to_string(),
+            PgType::Int8 => i64::from_be_bytes(read(value)?).to_string(),
+            PgType::Oid => u32::from_be_bytes(read(value)?).to_string(),
+            PgType::Float4 => format_float(f32::from_be_bytes(read(value)?), 6),
+            PgType::Float8 => format_float(f64::from_be_bytes(read(value)?), 15),
+            PgType::Numeric => format_numeric(value)?,
+            PgType::Text => String::from_utf8(value.to_vec())?,
+            PgType::Jsonb => match value.split_first() {
+                Some((1, json)) => String::from_utf8(json.to_vec())?,
+                _ => return Err("unsupported jsonb version".into()),
+            },
+            PgType::Uuid => crate::format_uuid(read(value)?),
+            PgType::Bytea => {
+                let hex: String = value.iter().map(|b| format!("{b:02x}")).collect();
+                format!("\\x{hex}")
+            }
+            PgType::Date => format_date(i32::from_be_bytes(read(value)?)),
+            PgType::Time => format_time(i64::from_be_bytes(read(value)?)),
+            PgType::Timestamp => format_timestamp(i64::from_be_bytes(read(value)?), ""),
+            PgType::Timestamptz => format_timestamp(i64::from_be_bytes(read(value)?), "+00"),
+        })
+    }
+}
+
+fn read<const N: usize>(value: &[u8]) -> Result<[u8; N], Box<dyn Error>> {
+    value
+        .try_into()
+        .map_err(|_| format!("expected {N} bytes, got {}", value.len()).into())
+}
+
+/// Parse a column spec of the form `name:type,name:type`.
+fn parse_columns(spec: &str) -> Result<(Vec<String>, Vec<PgType>), Box<dyn Error>> {
+    let mut names = Vec::new();
+    let mut types = Vec::new();
+    for column in split_spec(spec) {
+        let (name, ty) = column
+            .split_once(':')
+            .ok_or_else(|| format!("expected name:type, got {column}"))?;
+        names.push(name.trim().to_string());
+        types.push(PgType::parse(ty)?);
+    }
+    Ok((names, types))
+}
+
+/// Split at commas outside of parentheses, so that `numeric(10,2)` stays intact.
+fn split_spec(spec: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in spec.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&spec[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&spec[start..]);
+    parts.into_iter().filter(|p| !p.trim().is_empty()).collect()
+}
+
+/// Read COPY's binary format, with the columns given as `name:type,name:type`.
+pub(crate) fn read_binary(
+    input: impl Read + 'static,
+    columns: &str,
+) -> Result<(Vec<String>, crate::text::Rows), Box<dyn Error>> {
+    let (names, types) = parse_columns(columns)?;
+    let mut input = BufReader::new(input);
+
+    let mut header = [0u8; 19];
+    input.read_exact(&mut header)?;
+    if &header[..11] != SIGNATURE {
+        return Err("not a COPY binary file".into());
+    }
+    let extension_len = u32::from_be_bytes(header[15..19].try_into()?);
+    std::io::copy(
+        &mut input.by_ref().take(extension_len.into()),
+        &mut std::io::sink(),
+    )?;
+
+    let mut done = false;
+    let rows = std::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+        let row = read_row(&mut input, &types).transpose();
+        done = !matches!(row, Some(Ok(_)));
+        row
+    });
+    Ok((names, Box::new(rows)))
+}
+
+fn read_row(input: &mut impl Read, types: &[PgType]) -> Result<Option<Row>, Box<dyn Error>> {
+    let mut buf = [0u8; 2];
+    input.read_exact(&mut buf)?;
+    let fields = i16::from_be_bytes(buf);
+    if fields == -1 {
+        return Ok(None);
+    }
+    if fields as usize != types.len() {
+        return Err(format!("expected {} fields, got {fields}", types.len()).into());
+    }
+
+    let mut row = Vec::with_capacity(types.len());
+    for ty in types {
+        let mut buf = [0u8; 4];
+        input.read_exact(&mut buf)?;
+        let len = i32::from_be_bytes(buf);
+        if len < 0 {
+            row.push(None);
+            continue;
+        }
+        let mut value = vec![0u8; len as usize];
+        input.read_exact(&mut value)?;
+        row.push(Some(ty.render(&value)?));
+    }
+    Ok(Some(row))
+}
+
+trait Float: std::fmt::LowerExp + Copy {
+    fn is_nan(self) -> bool;
+    fn is_infinite(self) -> bool;
+    fn is_sign_negative(self) -> bool;
+    fn is_zero(self) -> bool;
+}
+
+macro_rules! impl_float {
+    ($t:ty) => {
+        impl Float for $t {
+            fn is_nan(self) -> bool {
+                <$t>::is_nan(self)
+            }
+            fn is_infinite(self) -> bool {
+                <$t>::is_infinite(self)
+            }
+            fn is_sign_negative(self) -> bool {
+                <$t>::is_sign_negative(self)
+            }
+            fn is_zero(self) -> bool {
+                self == 0.0
+            }
+        }
+    };
+}
+impl_float!(f32);
+impl_float!(f64);
+
+/// Shortest representation that round-trips, in exponential notation if the decimal exponent is
+/// below -4 or at least `precision` (6 for `real`, 15 for `double precision`), like `float8out`.
+fn format_float<F: Float>(v: F, precision: i32) -> String {
+    if v.is_nan() {
+        return "NaN".to_string();
+    }
+    let sign = if v.is_sign_negative() { "-" } else { "" };
+    if v.is_infinite() {
+        return format!("{sign}Infinity");
+    }
+    if v.is_zero() {
+        return format!("{sign}0");
+    }
+
+    let e = format!("{v:e}");
+    let (mantissa, exp) = e.split_once('e').expect("exponential format");
+    let exp: i32 = exp.parse().expect("exponent is a number");
+    let digits: String = mantissa.chars().filter(char::is_ascii_digit).collect();
+
+    if exp < -4 || exp >= precision {
+        let (first, rest) = digits.split_at(1);
+        let mantissa = if rest.is_empty() {
+            first.to_string()
+        } else {
+            format!("{first}.{rest}")
+        };
+        let exp_sign = if exp < 0 { '-' } else { '+' };
+        format!("{sign}{mantissa}e{exp_sign}{:02}", exp.abs())
+    } else if exp < 0 {
+        format!("{sign}0.{}{digits}", "0".repeat((-exp - 1) as usize))
+    } else {
+        let int_len = exp as usize + 1;
+        if digits.len() <= int_len {
+            format!("{sign}{digits}{}", "0".repeat(int_len - digits.len()))
+        } else {
+            format!("{sign}{}.{}", &digits[..int_len], &digits[int_len..])
+        }
+    }
+}
+
+/// Render numeric's binary representation: base 10000 digits with weight, sign and display scale.
+fn format_numeric(value: &[u8]) -> Result<String, Box<dyn Error>> {
+    if value.len() < 8 {
+        return Err("numeric too short".into());
+    }
+    let word = |i: usize| u16::from_be_bytes([value[2 * i], value[2 * i + 1]]);
+    let ndigits = word(0) as usize;
+    let weight = word(1) as i16 as i32;
+    let sign = word(2);
+    let dscale = word(3) as usize;
+    if value.len() != 8 + 2 * ndigits {
+        return Err("invalid numeric length".into());
+    }
+    match sign {
+        0x0000 | 0x4000 => {}
+        0xC000 => return Ok("NaN".to_string()),
+        0xD000 => return Ok("Infinity".to_string()),
+        0xF000 => return Ok("-Infinity".to_string()),
+        _ => return Err(format!("invalid numeric sign {sign:#x}").into()),
+    }
+    let digit = |i: i32| {
+        if i < 0 || i as usize >= ndigits {
+            0
+        } else {
+            word(4 + i as usize)
+        }
+    };
+
+    let mut out = String::new();
+    if sign == 0x4000 {
+        out.push('-');
+    }
+    if weight < 0 {
+        out.push('0');
+    } else {
+        out.push_str(&digit(0).to_string());
+        for i in 1..=weight {
+            out.push_str(&format!("{:04}", digit(i)));
+        }
+    }
+    if dscale > 0 {
+        let mut fraction = String::new();
+        let mut i = weight + 1;
+        while fraction.len() < dscale {
+            fraction.push_str(&format!("{:04}", digit(i)));
+            i += 1;
+        }
+        fraction.truncate(dscale);
+        out.push('.');
+        out.push_str(&fraction);
+    }
+    Ok(out)
+}
+
+/// Year, month, day of days since 1970-01-01, see <http://howardhinnant.github.io/date_algorithms.html>.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + i64::from(m <= 2);
+    (y, m, d)
+}
+
+/// ISO date, with year 0 and before rendered as BC like PostgreSQL does.
+fn format_ymd(days: i64) -> (String, bool) {
+    let (y, m, d) = civil_from_days(days + POSTGRES_EPOCH_DAYS);
+    if y > 0 {
+        (format!("{y:04}-{m:02}-{d:02}"), false)
+    } else {
+        (format!("{:04}-{m:02}-{d:02}", 1 - y), true)
+    }
+}
+
+fn format_date(days: i32) -> String {
+    match days {
+        i32::MAX => "infinity".to_string(),
+        i32::MIN => "-infinity".to_string(),
+        _ => match format_ymd(days.into()) {
+            (date, false) => date,
+            (date, true) => format!("{date} BC"),
+        },
+    }
+}
+
+fn format_time(usecs: i64) -> String {
+    let secs = usecs / 1_000_000;
+    let fraction = usecs % 1_000_000;
+    let mut out = format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60);
+    if fraction != 0 {
+        let fraction = format!("{fraction:06}");
+        out.push('.');
+        out.push_str(fraction.trim_end_matches('0'));
+    }
+    out
+}
+
+fn format_timestamp(usecs: i64, zone: &str) -> String {
+    match usecs {
+        i64::MAX => "infinity".to_string(),
+        i64::MIN => "-infinity".to_string(),
+        _ => {
+            let (date, bc) = format_ymd(usecs.div_euclid(USECS_PER_DAY));
+            let time = format_time(usecs.rem_euclid(USECS_PER_DAY));
+            let bc = if bc { " BC" } else { "" };
+            format!("{date} {time}{zone}{bc}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_float() {
+        for (v, golden) in [
+            (1.0, "1"),
+            (0.1, "0.1"),
+            (-2.5, "-2.5"),
+            (123456789012345.0, "123456789012345"),
+            (1e15, "1e+15"),
+            (1.5e300, "1.5e+300"),
+            (0.0001, "0.0001"),
+            (0.00001, "1e-05"),
+            (1.0 / 3.0, "0.3333333333333333"),
+            (-0.0, "-0"),
+            (f64::INFINITY, "Infinity"),
+            (f64::NAN, "NaN"),
+        ] {
+            assert_eq!(format_float(v, 15), golden, "using {v}");
+        }
+        assert_eq!(format_float(1.1f32, 6), "1.1");
+        assert_eq!(format_float(1234567f32, 6), "1.234567e+06");
+    }
+
+    #[test]
+    fn test_format_numeric() {
+        // 12345.678, numeric_send: ndigits 3, weight 1, sign +, dscale 3, digits 1 2345 6780
+        let value = [0, 3, 0, 1, 0, 0, 0, 3, 0, 1, 0x09, 0x29, 0x1a, 0x7c];
+        assert_eq!(format_numeric(&value).unwrap(), "12345.678");
+        // -0.05, dscale 2: ndigits 1, weight -1, digits 500
+        let value = [0, 1, 0xff, 0xff, 0x40, 0, 0, 2, 0x01, 0xf4];
+        assert_eq!(format_numeric(&value).unwrap(), "-0.05");
+        // 0.00 with dscale 2
+        let value = [0, 0, 0, 0, 0, 0, 0, 2];
+        assert_eq!(format_numeric(&value).unwrap(), "0.00");
+        // 10000
+        let value = [0, 1, 0, 1, 0, 0, 0, 0, 0, 1];
+        assert_eq!(format_numeric(&value).unwrap(), "10000");
+    }
+
+    #[test]
+    fn test_format_datetime() {
+        assert_eq!(format_date(0), "2000-01-01");
+        assert_eq!(format_date(-10957), "1970-01-01");
+        assert_eq!(format_date(9190), "2025-02-28");
+        assert_eq!(format_date(-730119), "0001-01-01");
+        assert_eq!(format_date(-730120), "0001-12-31 BC");
+        assert_eq!(format_time(45_296_789_000), "12:34:56.789");
+        assert_eq!(format_timestamp(-1, "+00"), "1999-12-31 23:59:59.999999+00");
+        assert_eq!(format_timestamp(0, ""), "2000-01-01 00:00:00");
+        assert_eq!(format_timestamp(i64::MAX, ""), "infinity");
+    }
+
+    #[test]
+    fn test_parse_columns() {
+        let (names, types) =
+            parse_columns("id:int8, amount:numeric(10,2),name:varchar(20)").unwrap();
+        assert_eq!(names, ["id", "amount", "name"]);
+        assert_eq!(types, [PgType::Int8, PgType::Numeric, PgType::Text]);
+        assert!(parse_columns("id:point").is_err());
+        assert!(parse_columns("id").is_err());
+    }
+
+    #[test]
+    fn test_read_binary() {
+        let mut file = SIGNATURE.to_vec();
+        file.extend(0u32.to_be_bytes());
+        file.extend(0u32.to_be_bytes());
+        // (1, 'a', NULL)
+        file.extend(3i16.to_be_bytes());
+        file.extend(4i32.to_be_bytes());
+        file.extend(1i32.to_be_bytes());
+        file.extend(1i32.to_be_bytes());
+        file.extend(b"a");
+        file.extend((-1i32).to_be_bytes());
+        // (2, 'b', true)
+        file.extend(3i16.to_be_bytes());
+        file.extend(4i32.to_be_bytes());
+        file.extend(2i32.to_be_bytes());
+        file.extend(1i32.to_be_bytes());
+        file.extend(b"b");
+        file.extend(1i32.to_be_bytes());
+        file.push(1);
+        file.extend((-1i16).to_be_bytes());
+
+        let (names, rows) =
+            read_binary(std::io::Cursor::new(file), "id:int4,name:text,flag:bool").unwrap();
+        assert_eq!(names, ["id", "name", "flag"]);
+        let rows: Vec<Row> = rows.collect::<Result<_, _>>().unwrap();
+        assert_eq!(
+            rows,
+            [
+                vec![Some("1".to_string()), Some("a".to_string()), None],
+                vec![
+                    Some("2".to_string()),
+                    Some("b".to_string()),
+                    Some("t".to_string())
+                ],
+            ]
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! Fingerprint the rows of CSV, TSV or COPY binary files exactly like the `pg_row_hashes` SQL
+//! functions, e.g. to verify an export against the database without loading it.
+
+mod binary;
+mod text;
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::PathBuf;
+
+use clap::{Parser, ValueEnum};
+use pg_row_hashes_fingerprint::{
+    checksum_fingerprint, id_fingerprint, Farmhash, NullPolicy, Seahash,
+};
+
+/// A row of the input file, `None` being `NULL`.
+type Row = Vec<Option<String>>;
+
+#[derive(Parser)]
+#[command(version, about)]
+/// Fingerprint the rows of a CSV, TSV or COPY binary file like the pg_row_hashes SQL functions.
+///
+/// Prints the `id_*` (if --id is given) and `checksum_*` fingerprint of every row, followed by
+/// the number of rows and the `bit_xor` of all checksums on stderr.
+struct Args {
+    /// Input file, with a header line unless binary, stdin if missing
+    file: Option<PathBuf>,
+
+    /// Input format, derived from the file extension by default
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+
+    /// Columns of binary input as `name:type`, e.g. `id:int8,name:text,created:timestamptz`
+    #[arg(long)]
+    columns: Option<String>,
+
+    /// Columns passed to `id_*`, in order
+    #[arg(long, value_delimiter = ',')]
+    id: Vec<String>,
+
+    /// Columns passed as key, value pairs to `checksum_*`, all columns by default
+    #[arg(long, value_delimiter = ',')]
+    checksum: Vec<String>,
+
+    /// Columns not passed to `checksum_*`
+    #[arg(long, value_delimiter = ',')]
+    exclude: Vec<String>,
+
+    #[arg(long, value_enum, default_value_t = Algorithm::Farmhash)]
+    algorithm: Algorithm,
+
+    /// Skip NULL values like `checksum_*_extendable`
+    #[arg(long)]
+    extendable: bool,
+
+    /// String representing NULL, by default the same as COPY's: an unquoted empty string for csv, `\N` for tsv
+    #[arg(long)]
+    null: Option<String>,
+
+    /// Only print the number of rows and the `bit_xor` of all checksums
+    #[arg(long)]
+    summary: bool,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Csv,
+    Tsv,
+    /// `COPY ... TO ... (FORMAT binary)`, requires --columns
+    Binary,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum Algorithm {
+    Farmhash,
+    Seahash,
+}
+
+impl Algorithm {
+    fn id(self, ids: &[String]) -> u128 {
+        match self {
+            Algorithm::Farmhash => id_fingerprint::<Farmhash, _>(ids.iter().cloned()),
+            Algorithm::Seahash => id_fingerprint::<Seahash, _>(ids.iter().cloned()).into(),
+        }
+    }
+
+    fn checksum(self, pairs: &[Option<String>], nulls: NullPolicy) -> u128 {
+        match self {
+            Algorithm::Farmhash => {
+                checksum_fingerprint::<Farmhash, _>(pairs.iter().cloned(), nulls)
+            }
+            Algorithm::Seahash => {
+                checksum_fingerprint::<Seahash, _>(pairs.iter().cloned(), nulls).into()
+            }
+        }
+    }
+
+    /// Render like PostgreSQL renders the `uuid` or `bigint` result.
+    fn format(self, digest: u128) -> String {
+        match self {
+            Algorithm::Farmhash => format_uuid(digest.to_le_bytes()),
+            Algorithm::Seahash => (digest as u64 as i64).to_string(),
+        }
+    }
+}
+
+fn format_uuid(bytes: [u8; 16]) -> String {
+    let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+/// Indices of the columns passed to `id_*` and `checksum_*`.
+struct Columns {
+    header: Vec<String>,
+    id: Vec<usize>,
+    checksum: Vec<usize>,
+}
+
+impl Columns {
+    fn new(header: Vec<String>, args: &Args) -> Result<Self, Box<dyn Error>> {
+        let index = |name: &String| {
+            header
+                .iter()
+                .position(|h| h == name)
+                .ok_or_else(|| format!("column {name} not found in header"))
+        };
+        let id = args.id.iter().map(index).collect::<Result<_, _>>()?;
+        let checksum: Vec<usize> = if args.checksum.is_empty() {
+            (0..header.len()).collect()
+        } else {
+            args.checksum.iter().map(index).collect::<Result<_, _>>()?
+        };
+        let exclude: Vec<usize> = args.exclude.iter().map(index).collect::<Result<_, _>>()?;
+        let checksum = checksum
+            .into_iter()
+            .filter(|i| !exclude.contains(i))
+            .collect();
+
+        Ok(Columns {
+            header,
+            id,
+            checksum,
+        })
+    }
+
+    fn ids(&self, row: &Row) -> Result<Vec<String>, Box<dyn Error>> {
+        self.id
+            .iter()
+            .map(|&i| {
+                row.get(i)
+                    .cloned()
+                    .flatten()
+                    .ok_or_else(|| format!("id column {} is NULL", self.header[i]).into())
+            })
+            .collect()
+    }
+
+    fn pairs(&self, row: &Row) -> Vec<Option<String>> {
+        self.checksum
+            .iter()
+            .flat_map(|&i| [Some(self.header[i].clone()), row.get(i).cloned().flatten()])
+            .collect()
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+
+    let format =
+        args.format
+            .unwrap_or_else(|| match args.file.as_ref().and_then(|f| f.extension()) {
+                Some(ext) if ext == "tsv" || ext == "tab" || ext == "txt" => Format::Tsv,
+                Some(ext) if ext == "bin" || ext == "pgcopy" => Format::Binary,
+                _ => Format::Csv,
+            });
+    let input: Box<dyn Read> = match &args.file {
+        Some(path) => Box::new(File::open(path)?),
+        None => Box::new(io::stdin().lock()),
+    };
+    let nulls = if args.extendable {
+        NullPolicy::Skip
+    } else {
+        NullPolicy::Keep
+    };
+
+    let (header, rows) = match format {
+        Format::Csv => text::read_csv(input, args.null.as_deref().unwrap_or(""))?,
+        Format::Tsv => text::read_tsv(input, args.null.as_deref().unwrap_or("\\N"))?,
+        Format::Binary => {
+            let columns = args
+                .columns
+                .as_deref()
+                .ok_or("binary input requires --columns")?;
+            binary::read_binary(input, columns)?
+        }
+    };
+    let columns = Columns::new(header, &args)?;
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    let mut count = 0u64;
+    let mut xor = 0u128;
+    for row in rows {
+        let row = row?;
+        let checksum = args.algorithm.checksum(&columns.pairs(&row), nulls);
+        count += 1;
+        xor ^= checksum;
+
+        if args.summary {
+            continue;
+        }
+        if !columns.id.is_empty() {
+            let id = args.algorithm.id(&columns.ids(&row)?);
+            write!(out, "{}\t", args.algorithm.format(id))?;
+        }
+        writeln!(out, "{}", args.algorithm.format(checksum))?;
+    }
+
+    if args.summary {
+        writeln!(out, "{count}\t{}", args.algorithm.format(xor))?;
+    } else {
+        out.flush()?;
+        eprintln!("rows: {count}");
+        eprintln!("bit_xor: {}", args.algorithm.format(xor));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pg_row_hashes_fingerprint::golden::{CHECKSUM_GOLDEN_TABLE, ID_GOLDEN_TABLE};
+    use pg_row_hashes_fingerprint::NullPolicy;
+
+    use super::Algorithm;
+
+    #[test]
+    fn test_format() {
+        for (_, ids, farmhash, seahash) in ID_GOLDEN_TABLE.iter() {
+            let ids: Vec<String> = ids.iter().map(|s| s.to_string()).collect();
+            assert_eq!(Algorithm::Farmhash.id(&ids), *farmhash);
+            assert_eq!(Algorithm::Seahash.id(&ids), u128::from(*seahash));
+        }
+
+        // SELECT id_farmhash('a', 'b', 'c'), id_seahash('a', 'b', 'c');
+        let ids = ["a", "b", "c"].map(String::from);
+        assert_eq!(
+            Algorithm::Farmhash.format(Algorithm::Farmhash.id(&ids)),
+            "0881825a-9f0a-4823-e156-5dbe41be5e8b"
+        );
+        assert_eq!(
+            Algorithm::Seahash.format(Algorithm::Seahash.id(&ids)),
+            "-4143832444633655910"
+        );
+    }
+
+    #[test]
+    fn test_checksum() {
+        for (pairs, farm_ext, _, farm, _) in CHECKSUM_GOLDEN_TABLE.iter() {
+            let pairs: Vec<Option<String>> = pairs.iter().map(|s| s.map(String::from)).collect();
+            assert_eq!(
+                Algorithm::Farmhash.checksum(&pairs, NullPolicy::Skip),
+                *farm_ext
+            );
+            assert_eq!(
+                Algorithm::Farmhash.checksum(&pairs, NullPolicy::Keep),
+                *farm
+            );
+        }
+    }
+}
@@ -0,0 +1,138 @@
+//! CSV and TSV input, following the conventions of `COPY ... (FORMAT csv, HEADER)` and
+//! `COPY ... (FORMAT text, HEADER)`.
+
+use std::error::Error;
+use std::io::Read;
+
+use csv::{ReaderBuilder, StringRecord};
+
+use crate::Row;
+
+pub(crate) type Rows = Box<dyn Iterator<Item = Result<Row, Box<dyn Error>>>>;
+
+/// Read CSV. As the csv crate does not tell quoted and unquoted fields apart, the empty string is
+/// always `NULL` with the default `null`.
+pub(crate) fn read_csv(
+    input: impl Read + 'static,
+    null: &str,
+) -> Result<(Vec<String>, Rows), Box<dyn Error>> {
+    let mut reader = ReaderBuilder::new().has_headers(true).from_reader(input);
+    let header = reader.headers()?.iter().map(String::from).collect();
+    let null = null.to_string();
+
+    let rows = reader.into_records().map(move |record| {
+        Ok(record?
+            .iter()
+            .map(|field| (field != null).then(|| field.to_string()))
+            .collect())
+    });
+    Ok((header, Box::new(rows)))
+}
+
+/// Read tab separated values with backslash escapes as written by COPY's text format.
+pub(crate) fn read_tsv(
+    input: impl Read + 'static,
+    null: &str,
+) -> Result<(Vec<String>, Rows), Box<dyn Error>> {
+    let mut reader = ReaderBuilder::new()
+        .delimiter(b'\t')
+        .quoting(false)
+        .has_headers(true)
+        .from_reader(input);
+    let header = record_fields(reader.headers()?, null)?
+        .into_iter()
+        .map(Option::unwrap_or_default)
+        .collect();
+    let null = null.to_string();
+
+    let rows = reader
+        .into_records()
+        .map(move |record| record_fields(&record?, &null));
+    Ok((header, Box::new(rows)))
+}
+
+fn record_fields(record: &StringRecord, null: &str) -> Result<Row, Box<dyn Error>> {
+    record
+        .iter()
+        .map(|field| {
+            if field == null {
+                Ok(None)
+            } else {
+                unescape(field).map(Some)
+            }
+        })
+        .collect()
+}
+
+/// Undo the backslash escapes of COPY's text format.
+fn unescape(field: &str) -> Result<String, Box<dyn Error>> {
+    if !field.contains('\\') {
+        return Ok(field.to_string());
+    }
+
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'\\' || i + 1 == bytes.len() {
+            out.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+        i += 1;
+        match bytes[i] {
+            b'b' => out.push(0x08),
+            b'f' => out.push(0x0c),
+            b'n' => out.push(b'\n'),
+            b'r' => out.push(b'\r'),
+            b't' => out.push(b'\t'),
+            b'v' => out.push(0x0b),
+            b'0'..=b'7' => {
+                let digits = bytes[i..]
+                    .iter()
+                    .take(3)
+                    .take_while(|b| (b'0'..=b'7').contains(b))
+                    .count();
+                let value = u32::from_str_radix(&field[i..i + digits], 8)?;
+                out.push(value as u8);
+                i += digits - 1;
+            }
+            b'x' if bytes.get(i + 1).is_some_and(u8::is_ascii_hexdigit) => {
+                let digits = bytes[i + 1..]
+                    .iter()
+                    .take(2)
+                    .take_while(|b| b.is_ascii_hexdigit())
+                    .count();
+                out.push(u8::from_str_radix(&field[i + 1..i + 1 + digits], 16)?);
+                i += digits;
+            }
+            other => out.push(other),
+        }
+        i += 1;
+    }
+    Ok(String::from_utf8(out)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::unescape;
+
+    #[test]
+    fn test_unescape() {
+        for (field, golden) in [
+            ("plain", "plain"),
+            ("a\\tb", "a\tb"),
+            ("line\\nbreak\\r", "line\nbreak\r"),
+            ("back\\\\slash", "back\\slash"),
+            ("\\101\\x42c", "ABc"),
+            ("\\N", "N"),
+            ("trailing\\", "trailing\\"),
+        ] {
+            assert_eq!(
+                unescape(field).expect("valid escape"),
+                golden,
+                "using {field}"
+            );
+        }
+    }
+}
@@ -0,0 +1,381 @@
+//! Bloom filters over fingerprints.
+//!
+//! `bloom_agg()` updates a single filter in place, so its transition state is `internal`, allocated
+//! in the aggregate's memory context and only serialized to pass it between parallel workers. Such
+//! aggregates are defined in SQL below instead of with `#[pg_aggregate]`.
+
+use pg_row_hashes_fingerprint::splitmix64;
+use pgrx::{prelude::*, Internal, Uuid};
+use serde::{Deserialize, Serialize};
+
+use crate::aggregate_state;
+
+extension_sql!(
+    r#"
+CREATE AGGREGATE bloom_agg(value bigint, capacity integer, fp_rate double precision) (
+    SFUNC = bloom_agg_bigint_state,
+    STYPE = internal,
+    FINALFUNC = bloom_agg_finalize,
+    COMBINEFUNC = bloom_agg_combine,
+    SERIALFUNC = bloom_agg_serialize,
+    DESERIALFUNC = bloom_agg_deserialize,
+    PARALLEL = SAFE
+);
+CREATE AGGREGATE bloom_agg(value uuid, capacity integer, fp_rate double precision) (
+    SFUNC = bloom_agg_uuid_state,
+    STYPE = internal,
+    FINALFUNC = bloom_agg_finalize,
+    COMBINEFUNC = bloom_agg_combine,
+    SERIALFUNC = bloom_agg_serialize,
+    DESERIALFUNC = bloom_agg_deserialize,
+    PARALLEL = SAFE
+);
+"#,
+    name = "bloom_agg",
+    requires = [
+        bloom_agg_bigint_state,
+        bloom_agg_uuid_state,
+        bloom_agg_finalize,
+        bloom_agg_combine,
+        bloom_agg_serialize,
+        bloom_agg_deserialize,
+    ],
+);
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, PostgresType, Serialize, Deserialize)]
+#[serde(from = "BloomParts")]
+#[pg_binary_protocol]
+/// Bloom filter over fingerprints.
+///
+/// The default value with `k = 0` is an empty filter not sized yet, the result of `bloom_agg()`
+/// over no values.
+pub struct fingerprint_bloom {
+    k: u32,
+    bits: Vec<u64>,
+}
+
+/// Most hash functions of a filter.
+const MAX_K: u32 = 32;
+
+/// Fields of a filter as read from its text or binary input, before they are checked.
+#[derive(Deserialize)]
+struct BloomParts {
+    k: u32,
+    bits: Vec<u64>,
+}
+
+impl From<BloomParts> for fingerprint_bloom {
+    fn from(BloomParts { k, bits }: BloomParts) -> Self {
+        fingerprint_bloom::new(k, bits)
+    }
+}
+
+impl fingerprint_bloom {
+    /// A filter read from its input, raising an error if it can't be used.
+    fn new(k: u32, bits: Vec<u64>) -> Self {
+        if k > MAX_K {
+            error!("bloom filter must have at most {MAX_K} hash functions, got {k}");
+        }
+        if (k == 0) != bits.is_empty() {
+            error!(
+                "bloom filter with {k} hash functions can't have {} bits",
+                bits.len() * 64
+            );
+        }
+        fingerprint_bloom { k, bits }
+    }
+
+    /// Size a filter for `capacity` values with the false positive rate `fp_rate`.
+    fn with_capacity(capacity: i32, fp_rate: f64) -> Self {
+        if capacity <= 0 {
+            error!("bloom filter capacity must be positive, got {capacity}");
+        }
+        if !(fp_rate > 0.0 && fp_rate < 1.0) {
+            error!("bloom filter false positive rate must be between 0 and 1, got {fp_rate}");
+        }
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-f64::from(capacity) * fp_rate.ln() / (ln2 * ln2)).ceil();
+        let words = ((bits / 64.0).ceil() as usize).max(1);
+        let k = ((words * 64) as f64 / f64::from(capacity) * ln2).round();
+        fingerprint_bloom {
+            k: (k as u32).clamp(1, MAX_K),
+            bits: vec![0; words],
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.k == 0
+    }
+
+    /// Bit positions of a value using double hashing, fingerprints are already uniformly
+    /// distributed.
+    fn positions(&self, h1: u64, h2: u64) -> impl Iterator<Item = usize> + '_ {
+        let len = self.bits.len() as u64 * 64;
+        let h2 = h2 | 1;
+        (0..u64::from(self.k)).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % len) as usize)
+    }
+
+    fn insert(&mut self, (h1, h2): (u64, u64)) {
+        for p in self.positions(h1, h2).collect::<Vec<_>>() {
+            self.bits[p / 64] |= 1 << (p % 64);
+        }
+    }
+
+    fn contains(&self, (h1, h2): (u64, u64)) -> bool {
+        !self.is_empty()
+            && self
+                .positions(h1, h2)
+                .all(|p| self.bits[p / 64] & (1 << (p % 64)) != 0)
+    }
+
+    /// Add the values of `other`, a filter of the same capacity and false positive rate.
+    fn merge(&mut self, other: &Self) {
+        if other.is_empty() {
+            return;
+        }
+        if self.is_empty() {
+            *self = other.clone();
+            return;
+        }
+        if self.k != other.k || self.bits.len() != other.bits.len() {
+            error!("cannot combine bloom filters of different capacity or false positive rate");
+        }
+        for (word, other) in self.bits.iter_mut().zip(&other.bits) {
+            *word |= other;
+        }
+    }
+
+    fn union(mut self, other: Self) -> Self {
+        self.merge(&other);
+        self
+    }
+
+    /// `k` and the words of the filter in little endian order.
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(4 + self.bits.len() * 8);
+        bytes.extend_from_slice(&self.k.to_le_bytes());
+        for word in &self.bits {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// The filter of `to_bytes()`.
+    fn from_bytes(bytes: &[u8]) -> Self {
+        if bytes.len() < 4 || (bytes.len() - 4) % 8 != 0 {
+            error!("invalid bloom filter of {} bytes", bytes.len());
+        }
+        let (k, bits) = bytes.split_at(4);
+        fingerprint_bloom::new(
+            u32::from_le_bytes(k.try_into().expect("k has 4 bytes")),
+            bits.chunks_exact(8)
+                .map(|word| u64::from_le_bytes(word.try_into().expect("words have 8 bytes")))
+                .collect(),
+        )
+    }
+}
+
+/// Split a `bigint` fingerprint into the two hashes used for double hashing.
+fn bigint_hashes(value: i64) -> (u64, u64) {
+    let h1 = value as u64;
+    (h1, splitmix64(h1))
+}
+
+/// Split a `uuid` fingerprint into the two hashes used for double hashing.
+fn uuid_hashes(value: &Uuid) -> (u64, u64) {
+    let v = u128::from_le_bytes(*value.as_bytes());
+    (v as u64, (v >> 64) as u64)
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Whether the `bigint` fingerprint may be in the bloom filter.
+pub fn bloom_contains(bloom: fingerprint_bloom, value: i64) -> bool {
+    bloom.contains(bigint_hashes(value))
+}
+
+#[pg_extern(
+    strict,
+    immutable,
+    parallel_safe,
+    create_or_replace,
+    name = "bloom_contains"
+)]
+/// Whether the `uuid` fingerprint may be in the bloom filter.
+pub fn bloom_contains_uuid(bloom: fingerprint_bloom, value: Uuid) -> bool {
+    bloom.contains(uuid_hashes(&value))
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Union of two bloom filters of the same capacity and false positive rate.
+pub fn bloom_union(a: fingerprint_bloom, b: fingerprint_bloom) -> fingerprint_bloom {
+    a.union(b)
+}
+
+#[pg_extern(immutable, parallel_safe, create_or_replace)]
+/// Transition function of `bloom_agg(bigint, ...)`, sizing the filter by the first value.
+pub fn bloom_agg_bigint_state(
+    mut state: Internal,
+    value: Option<i64>,
+    capacity: Option<i32>,
+    fp_rate: Option<f64>,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> Internal {
+    if let (Some(value), Some(capacity), Some(fp_rate)) = (value, capacity, fp_rate) {
+        unsafe {
+            aggregate_state(fcinfo, &mut state, || {
+                fingerprint_bloom::with_capacity(capacity, fp_rate)
+            })
+        }
+        .insert(bigint_hashes(value));
+    }
+    state
+}
+
+#[pg_extern(immutable, parallel_safe, create_or_replace)]
+/// Transition function of `bloom_agg(uuid, ...)`, sizing the filter by the first value.
+pub fn bloom_agg_uuid_state(
+    mut state: Internal,
+    value: Option<Uuid>,
+    capacity: Option<i32>,
+    fp_rate: Option<f64>,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> Internal {
+    if let (Some(value), Some(capacity), Some(fp_rate)) = (value, capacity, fp_rate) {
+        unsafe {
+            aggregate_state(fcinfo, &mut state, || {
+                fingerprint_bloom::with_capacity(capacity, fp_rate)
+            })
+        }
+        .insert(uuid_hashes(&value));
+    }
+    state
+}
+
+#[pg_extern(immutable, parallel_safe, create_or_replace)]
+/// Final function of `bloom_agg()`, an empty filter if there were no values.
+pub fn bloom_agg_finalize(state: Internal) -> fingerprint_bloom {
+    unsafe { state.get::<fingerprint_bloom>() }
+        .cloned()
+        .unwrap_or_default()
+}
+
+#[pg_extern(immutable, parallel_safe, create_or_replace)]
+/// Combine function of `bloom_agg()`.
+pub fn bloom_agg_combine(
+    mut state: Internal,
+    other: Internal,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> Internal {
+    if let Some(other) = unsafe { other.get::<fingerprint_bloom>() } {
+        unsafe { aggregate_state(fcinfo, &mut state, fingerprint_bloom::default) }.merge(other);
+    }
+    state
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Serialization function of `bloom_agg()`, to pass the state between parallel workers.
+pub fn bloom_agg_serialize(state: Internal) -> Vec<u8> {
+    unsafe { state.get::<fingerprint_bloom>() }
+        .expect("the state is not NULL")
+        .to_bytes()
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Deserialization function of `bloom_agg()`.
+pub fn bloom_agg_deserialize(
+    bytes: &[u8],
+    _state: Internal,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> Internal {
+    let mut state = Internal::from(None);
+    unsafe { aggregate_state(fcinfo, &mut state, || fingerprint_bloom::from_bytes(bytes)) };
+    state
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pg_schema]
+mod tests {
+    use pgrx::prelude::*;
+
+    use super::{bigint_hashes, fingerprint_bloom};
+
+    #[test]
+    fn test_bloom_sizing() {
+        // 1000 values at 1% need 9586 bits and 7 hash functions
+        let bloom = fingerprint_bloom::with_capacity(1000, 0.01);
+        assert_eq!(bloom.bits.len(), 150);
+        assert_eq!(bloom.k, 7);
+    }
+
+    #[test]
+    fn test_bloom_false_positive_rate() {
+        let mut bloom = fingerprint_bloom::with_capacity(1000, 0.01);
+        for i in 0..1000 {
+            bloom.insert(bigint_hashes(i));
+        }
+        assert!((0..1000).all(|i| bloom.contains(bigint_hashes(i))));
+        let false_positives = (1000..101_000)
+            .filter(|&i| bloom.contains(bigint_hashes(i)))
+            .count();
+        assert!(false_positives < 2000, "{false_positives} false positives");
+    }
+
+    #[test]
+    fn test_bloom_union() {
+        let mut a = fingerprint_bloom::with_capacity(100, 0.01);
+        let mut b = a.clone();
+        a.insert(bigint_hashes(1));
+        b.insert(bigint_hashes(2));
+        let union = a.union(b);
+        assert!(union.contains(bigint_hashes(1)));
+        assert!(union.contains(bigint_hashes(2)));
+        assert_eq!(fingerprint_bloom::default().union(union.clone()), union);
+        assert_eq!(fingerprint_bloom::from_bytes(&union.to_bytes()), union);
+    }
+
+    #[pg_test]
+    fn pg_test_bloom_agg() {
+        Spi::run(
+            "CREATE TABLE bloom_test AS
+             SELECT id_seahash(i::text) AS s, id_farmhash(i::text) AS f
+             FROM generate_series(1, 1000) i;",
+        )
+        .expect("SPI failed");
+
+        for (query, golden) in [
+            (
+                "SELECT bool_and(bloom_contains(b, s)) FROM bloom_test, (SELECT bloom_agg(s, 1000, 0.01) AS b FROM bloom_test) b;",
+                true,
+            ),
+            (
+                "SELECT bool_and(bloom_contains(b, f)) FROM bloom_test, (SELECT bloom_agg(f, 1000, 0.01) AS b FROM bloom_test) b;",
+                true,
+            ),
+            (
+                "SELECT bloom_contains(bloom_agg(s, 1000, 0.01), id_seahash('a')) FROM bloom_test;",
+                false,
+            ),
+            (
+                "SELECT bloom_contains(bloom_agg(s, 1000, 0.01), id_seahash('1')) FROM bloom_test WHERE false;",
+                false,
+            ),
+            (
+                "SELECT bloom_contains(bloom_union(
+                    (SELECT bloom_agg(s, 1000, 0.01) FROM bloom_test WHERE s < 0),
+                    (SELECT bloom_agg(s, 1000, 0.01) FROM bloom_test WHERE s >= 0)
+                 ), id_seahash('1'));",
+                true,
+            ),
+        ] {
+            let result = Spi::get_one::<bool>(query);
+            assert_eq!(result, Ok(Some(golden)), "using {query}");
+        }
+    }
+
+    #[pg_test(error = "bloom filter with 3 hash functions can't have 0 bits")]
+    fn pg_test_bloom_input_without_bits() {
+        Spi::run(r#"SELECT bloom_contains('{"k":3,"bits":[]}'::fingerprint_bloom, 1::bigint);"#)
+            .expect("SPI failed");
+    }
+}
@@ -0,0 +1,187 @@
+use pg_row_hashes_fingerprint::{splitmix64, Algorithm as _, Farmhash};
+use pgrx::{prelude::*, Uuid};
+
+/// Random values of the Gear rolling hash, one per byte value, generated with splitmix64.
+const GEAR: [u64; 256] = gear_table();
+
+const fn gear_table() -> [u64; 256] {
+    let mut table = [0; 256];
+    let mut state: u64 = 0;
+    let mut i = 0;
+    while i < 256 {
+        table[i] = splitmix64(state);
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        i += 1;
+    }
+    table
+}
+
+/// FastCDC chunker with normalized chunking: chunks are between a quarter and eight times the
+/// average size, cut points before the average size are harder to hit than after it.
+struct Chunker {
+    min: usize,
+    avg: usize,
+    max: usize,
+    mask_small: u64,
+    mask_large: u64,
+}
+
+impl Chunker {
+    fn new(avg_size: i32) -> Self {
+        if !(64..=1 << 28).contains(&avg_size) {
+            error!("average chunk size must be between 64 and 268435456, got {avg_size}");
+        }
+        let avg = avg_size as usize;
+        let bits = avg.ilog2();
+        // the gear hash mixes into the high bits, so the masks select those
+        let mask = |bits: u32| !(u64::MAX >> bits);
+        Chunker {
+            min: avg / 4,
+            avg,
+            max: avg * 8,
+            mask_small: mask(bits + 2),
+            mask_large: mask(bits - 2),
+        }
+    }
+
+    /// Length of the chunk at the start of `data`.
+    fn cut(&self, data: &[u8]) -> usize {
+        if data.len() <= self.min {
+            return data.len();
+        }
+        let normal = data.len().min(self.avg);
+        let end = data.len().min(self.max);
+
+        let mut hash = 0u64;
+        for (i, &b) in data.iter().enumerate().take(end).skip(self.min) {
+            hash = (hash << 1).wrapping_add(GEAR[b as usize]);
+            let mask = if i < normal {
+                self.mask_small
+            } else {
+                self.mask_large
+            };
+            if hash & mask == 0 {
+                return i + 1;
+            }
+        }
+        end
+    }
+
+    /// Offset and length of all chunks of `data`.
+    fn chunks(&self, data: &[u8]) -> Vec<(usize, usize)> {
+        let mut chunks = Vec::new();
+        let mut offset = 0;
+        while offset < data.len() {
+            let length = self.cut(&data[offset..]);
+            chunks.push((offset, length));
+            offset += length;
+        }
+        chunks
+    }
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Split bytes into content-defined chunks of about `avg_size` bytes using FastCDC.
+///
+/// The fingerprint of a chunk is the same as `id_farmhash` of its bytes.
+pub fn content_chunks(
+    data: &[u8],
+    avg_size: default!(i32, 8192),
+) -> TableIterator<
+    'static,
+    (
+        name!(offset, i64),
+        name!(length, i32),
+        name!(fingerprint, Uuid),
+    ),
+> {
+    let chunks = Chunker::new(avg_size)
+        .chunks(data)
+        .into_iter()
+        .map(|(offset, length)| {
+            let fingerprint = Farmhash::fingerprint(&data[offset..offset + length]);
+            (
+                offset as i64,
+                length as i32,
+                Uuid::from_bytes(fingerprint.to_le_bytes()),
+            )
+        })
+        .collect::<Vec<_>>();
+    TableIterator::new(chunks)
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pg_schema]
+mod tests {
+    use pgrx::prelude::*;
+
+    use pg_row_hashes_fingerprint::splitmix64;
+
+    use super::Chunker;
+
+    fn random_bytes(len: u64, seed: u64) -> Vec<u8> {
+        (0..len)
+            .map(|i| splitmix64(i ^ (seed << 32)) as u8)
+            .collect()
+    }
+
+    #[test]
+    fn test_chunk_sizes() {
+        let chunker = Chunker::new(1024);
+        let data = random_bytes(1 << 20, 1);
+        let chunks = chunker.chunks(&data);
+
+        assert_eq!(chunks.iter().map(|(_, len)| len).sum::<usize>(), data.len());
+        assert!(chunks.windows(2).all(|w| w[0].0 + w[0].1 == w[1].0));
+        assert!(chunks[..chunks.len() - 1]
+            .iter()
+            .all(|&(_, len)| (256..=8192).contains(&len)));
+        let avg = data.len() / chunks.len();
+        assert!((512..2048).contains(&avg), "average chunk size {avg}");
+    }
+
+    #[test]
+    fn test_chunks_are_content_defined() {
+        let chunker = Chunker::new(1024);
+        let data = random_bytes(1 << 16, 2);
+        let mut edited = random_bytes(100, 3);
+        edited.extend(&data);
+
+        let chunks = |data: &[u8]| {
+            chunker
+                .chunks(data)
+                .into_iter()
+                .map(|(offset, len)| data[offset..offset + len].to_vec())
+                .collect::<Vec<_>>()
+        };
+        let original = chunks(&data);
+        let edited = chunks(&edited);
+        let shared = original.iter().filter(|c| edited.contains(c)).count();
+        assert!(
+            shared >= original.len() - 2,
+            "only {shared} of {} chunks shared",
+            original.len()
+        );
+    }
+
+    #[test]
+    fn test_small_input() {
+        let chunker = Chunker::new(1024);
+        assert!(chunker.chunks(&[]).is_empty());
+        assert_eq!(chunker.chunks(&[1, 2, 3]), [(0, 3)]);
+    }
+
+    #[pg_test]
+    fn pg_test_content_chunks() {
+        let result = Spi::get_one::<bool>(
+            "WITH data AS (
+                SELECT string_agg(md5(i::text), '')::bytea AS d FROM generate_series(1, 2000) i
+             )
+             SELECT bool_and(fingerprint = id_farmhash(substring(d FROM \"offset\"::int + 1 FOR length)))
+                AND sum(length) = length(d)
+             FROM data, content_chunks(d, 1024)
+             GROUP BY d;",
+        );
+        assert_eq!(result, Ok(Some(true)));
+    }
+}
@@ -0,0 +1,179 @@
+//! Conversions of fingerprints to and from the unsigned integers the algorithms return, so they
+//! can be compared with other systems' output, see `pg_row_hashes_fingerprint::hex`.
+//!
+//! `bigint` fingerprints are seahash's u64 bytecast to i64, `uuid` fingerprints farmhash's u128
+//! in little endian order.
+
+use pg_row_hashes_fingerprint::hex;
+use pgrx::{prelude::*, AnyNumeric, Uuid};
+
+use crate::{farmhash_uuid, seahash_bigint};
+
+/// The u64 of a `bigint` fingerprint.
+fn seahash_u64(fingerprint: i64) -> u64 {
+    fingerprint as u64
+}
+
+/// The u128 of a `uuid` fingerprint.
+fn farmhash_u128(fingerprint: Uuid) -> u128 {
+    u128::from_le_bytes(*fingerprint.as_bytes())
+}
+
+#[pg_extern(
+    strict,
+    immutable,
+    parallel_safe,
+    create_or_replace,
+    name = "fingerprint_to_hex"
+)]
+/// Hexadecimal of a bigint fingerprint as unsigned 64 bit integer, 16 digits
+pub fn fingerprint_to_hex_bigint(fingerprint: i64) -> String {
+    hex::to_hex64(seahash_u64(fingerprint))
+}
+
+#[pg_extern(
+    strict,
+    immutable,
+    parallel_safe,
+    create_or_replace,
+    name = "fingerprint_to_hex"
+)]
+/// Hexadecimal of a uuid fingerprint as unsigned 128 bit integer, 32 digits
+pub fn fingerprint_to_hex_uuid(fingerprint: Uuid) -> String {
+    hex::to_hex128(farmhash_u128(fingerprint))
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Bigint fingerprint of the hexadecimal of an unsigned 64 bit integer
+pub fn fingerprint_from_hex(hex: &str) -> i64 {
+    match hex::from_hex64(hex) {
+        Some(fingerprint) => seahash_bigint(fingerprint),
+        None => error!("invalid 64 bit fingerprint: \"{hex}\""),
+    }
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Uuid fingerprint of the hexadecimal of an unsigned 128 bit integer
+pub fn fingerprint128_from_hex(hex: &str) -> Uuid {
+    match hex::from_hex128(hex) {
+        Some(fingerprint) => farmhash_uuid(fingerprint),
+        None => error!("invalid 128 bit fingerprint: \"{hex}\""),
+    }
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Decimal of a bigint fingerprint as unsigned 64 bit integer
+pub fn fingerprint_to_u64_text(fingerprint: i64) -> String {
+    seahash_u64(fingerprint).to_string()
+}
+
+#[pg_extern(
+    strict,
+    immutable,
+    parallel_safe,
+    create_or_replace,
+    name = "fingerprint_to_numeric"
+)]
+/// Bigint fingerprint as unsigned 64 bit integer
+pub fn fingerprint_to_numeric_bigint(fingerprint: i64) -> AnyNumeric {
+    AnyNumeric::from(seahash_u64(fingerprint))
+}
+
+#[pg_extern(
+    strict,
+    immutable,
+    parallel_safe,
+    create_or_replace,
+    name = "fingerprint_to_numeric"
+)]
+/// Uuid fingerprint as unsigned 128 bit integer
+pub fn fingerprint_to_numeric_uuid(fingerprint: Uuid) -> AnyNumeric {
+    farmhash_u128(fingerprint)
+        .to_string()
+        .parse()
+        .expect("integers are numerics")
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Bigint fingerprint of an unsigned 64 bit integer
+pub fn fingerprint_from_numeric(value: AnyNumeric) -> i64 {
+    match value.to_string().parse() {
+        Ok(fingerprint) => seahash_bigint(fingerprint),
+        Err(_) => error!("{value} is not an unsigned 64 bit integer"),
+    }
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Uuid fingerprint of an unsigned 128 bit integer
+pub fn fingerprint128_from_numeric(value: AnyNumeric) -> Uuid {
+    match value.to_string().parse() {
+        Ok(fingerprint) => farmhash_uuid(fingerprint),
+        Err(_) => error!("{value} is not an unsigned 128 bit integer"),
+    }
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pg_schema]
+mod tests {
+    use pg_row_hashes_fingerprint::{id_fingerprint, Farmhash, Seahash};
+    use pgrx::prelude::*;
+
+    #[pg_test]
+    fn pg_test_conversions() {
+        let seahash = id_fingerprint::<Seahash, _>(["a", "b", "c"]);
+        let farmhash = id_fingerprint::<Farmhash, _>(["a", "b", "c"]);
+        for (query, golden) in [
+            (
+                "SELECT fingerprint_to_hex(id_seahash('a', 'b', 'c'));",
+                format!("{seahash:016x}"),
+            ),
+            (
+                "SELECT fingerprint_to_hex(id_farmhash('a', 'b', 'c'));",
+                format!("{farmhash:032x}"),
+            ),
+            (
+                "SELECT fingerprint_to_u64_text(id_seahash('a', 'b', 'c'));",
+                seahash.to_string(),
+            ),
+            (
+                "SELECT fingerprint_to_numeric(id_seahash('a', 'b', 'c'))::text;",
+                seahash.to_string(),
+            ),
+            (
+                "SELECT fingerprint_to_numeric(id_farmhash('a', 'b', 'c'))::text;",
+                farmhash.to_string(),
+            ),
+            ("SELECT fingerprint_to_hex(-1::bigint);", "f".repeat(16)),
+            ("SELECT fingerprint_to_u64_text(-1);", u64::MAX.to_string()),
+        ] {
+            let result = Spi::get_one::<String>(query);
+            assert_eq!(result, Ok(Some(golden)), "using {query}");
+        }
+
+        // conversions round-trip exactly
+        for (query, golden) in [
+            (
+                "SELECT bool_and(fingerprint_from_hex(fingerprint_to_hex(h)) = h
+                    AND fingerprint_from_numeric(fingerprint_to_numeric(h)) = h)
+                 FROM (SELECT id_seahash(i::text) FROM generate_series(1, 1000) i
+                       UNION ALL VALUES (0), (-1), (9223372036854775807), (-9223372036854775807 - 1)) s(h);",
+                true,
+            ),
+            (
+                "SELECT bool_and(fingerprint128_from_hex(fingerprint_to_hex(h)) = h
+                    AND fingerprint128_from_numeric(fingerprint_to_numeric(h)) = h)
+                 FROM (SELECT id_farmhash(i::text) FROM generate_series(1, 1000) i) s(h);",
+                true,
+            ),
+            (
+                "SELECT fingerprint_from_hex('0xFFFFFFFFFFFFFFFF') = -1
+                    AND fingerprint_from_numeric(18446744073709551615) = -1
+                    AND fingerprint128_from_numeric(1) = fingerprint128_from_hex('1');",
+                true,
+            ),
+        ] {
+            let result = Spi::get_one::<bool>(query);
+            assert_eq!(result, Ok(Some(golden)), "using {query}");
+        }
+    }
+}
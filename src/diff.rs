@@ -0,0 +1,165 @@
+use pgrx::{prelude::*, spi, PgRelation};
+
+use crate::extension_schema;
+
+#[pg_extern(stable, create_or_replace)]
+/// Compare two relations row by row on the given key columns using `checksum_farmhash_record`.
+///
+/// Returns the keys of rows only present in one of the relations (`missing_left`, `missing_right`)
+/// or having a different fingerprint (`changed`). Keys may contain `NULL` values, rows sharing a
+/// key are `changed` unless both relations have the same rows with that key.
+fn row_hash_diff(
+    left: PgRelation,
+    right: PgRelation,
+    key_columns: Vec<String>,
+) -> Result<
+    TableIterator<'static, (name!(key, Vec<Option<String>>), name!(status, String))>,
+    spi::Error,
+> {
+    if key_columns.is_empty() {
+        error!("row_hash_diff() requires at least one key column");
+    }
+    let query = diff_query(
+        &extension_schema()?,
+        &qualified_name(&left),
+        &qualified_name(&right),
+        &key_columns,
+    );
+
+    let rows = Spi::connect(|client| {
+        client
+            .select(&query, None, &[])?
+            .map(|row| {
+                Ok((
+                    row.get::<Vec<Option<String>>>(1)?.unwrap_or_default(),
+                    row.get::<String>(2)?.unwrap_or_default(),
+                ))
+            })
+            .collect::<Result<Vec<_>, spi::Error>>()
+    })?;
+    Ok(TableIterator::new(rows))
+}
+
+pub(crate) fn qualified_name(relation: &PgRelation) -> String {
+    spi::quote_qualified_identifier(relation.namespace(), relation.name())
+}
+
+fn diff_query(schema: &str, left: &str, right: &str, key_columns: &[String]) -> String {
+    let schema = spi::quote_identifier(schema);
+    let key = key_columns
+        .iter()
+        .map(|c| format!("t.{}::text", spi::quote_identifier(c)))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    // Rows sharing a key are compared as a whole, by the sorted fingerprints of all of them.
+    // Array equality treats NULL elements as equal, so NULL key columns match as well.
+    let fingerprints = |relation: &str| {
+        format!(
+            "SELECT key, array_agg(fingerprint ORDER BY fingerprint) AS fingerprints FROM (
+                SELECT ARRAY[{key}] AS key, {schema}.checksum_farmhash_record(t) AS fingerprint
+                FROM {relation} t
+            ) t GROUP BY key"
+        )
+    };
+    format!(
+        "WITH l AS ({}), r AS ({})
+        SELECT coalesce(l.key, r.key),
+            CASE
+                WHEN l.key IS NULL THEN 'missing_left'
+                WHEN r.key IS NULL THEN 'missing_right'
+                ELSE 'changed'
+            END
+        FROM l FULL OUTER JOIN r ON l.key = r.key
+        WHERE l.fingerprints IS DISTINCT FROM r.fingerprints
+        ORDER BY 1",
+        fingerprints(left),
+        fingerprints(right),
+    )
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pg_schema]
+mod tests {
+    use pgrx::prelude::*;
+
+    #[pg_test]
+    fn pg_test_row_hash_diff() {
+        Spi::run(
+            "CREATE TABLE diff_left (id int, sub int, name text);
+             CREATE TABLE diff_right (id int, sub int, name text);
+             INSERT INTO diff_left VALUES (1, 1, 'a'), (2, 1, 'b'), (3, 1, 'c'), (4, 1, NULL);
+             INSERT INTO diff_right VALUES (1, 1, 'a'), (2, 1, 'x'), (4, 1, NULL), (5, 1, 'e');",
+        )
+        .expect("SPI failed");
+
+        let result = Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT key, status FROM row_hash_diff('diff_left', 'diff_right', '{id,sub}');",
+                    None,
+                    &[],
+                )?
+                .map(|row| {
+                    Ok((
+                        row.get::<Vec<Option<String>>>(1)?.expect("got None"),
+                        row.get::<String>(2)?.expect("got None"),
+                    ))
+                })
+                .collect::<Result<Vec<_>, pgrx::spi::Error>>()
+        })
+        .expect("SPI failed");
+
+        let key = |id: &str| vec![Some(id.to_string()), Some("1".to_string())];
+        assert_eq!(
+            result,
+            vec![
+                (key("2"), "changed".to_string()),
+                (key("3"), "missing_right".to_string()),
+                (key("5"), "missing_left".to_string()),
+            ]
+        );
+    }
+
+    #[pg_test]
+    fn pg_test_row_hash_diff_null_and_duplicate_keys() {
+        Spi::run(
+            "CREATE TABLE diff_left (id int, sub int, name text);
+             CREATE TABLE diff_right (id int, sub int, name text);
+             INSERT INTO diff_left VALUES
+                (1, NULL, 'a'), (2, 1, 'b'), (2, 1, 'b'), (3, 1, 'c'), (3, 1, 'c'), (4, NULL, 'd');
+             INSERT INTO diff_right VALUES
+                (1, NULL, 'a'), (2, 1, 'b'), (3, 1, 'c'), (3, 1, 'c'), (4, NULL, 'x');",
+        )
+        .expect("SPI failed");
+
+        let result = Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT key, status FROM row_hash_diff('diff_left', 'diff_right', '{id,sub}');",
+                    None,
+                    &[],
+                )?
+                .map(|row| {
+                    Ok((
+                        row.get::<Vec<Option<String>>>(1)?.expect("got None"),
+                        row.get::<String>(2)?.expect("got None"),
+                    ))
+                })
+                .collect::<Result<Vec<_>, pgrx::spi::Error>>()
+        })
+        .expect("SPI failed");
+
+        // key (2, 1) is in the left relation twice, key (4, NULL) changed once
+        assert_eq!(
+            result,
+            vec![
+                (
+                    vec![Some("2".to_string()), Some("1".to_string())],
+                    "changed".to_string()
+                ),
+                (vec![Some("4".to_string()), None], "changed".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use pgrx::{prelude::*, GucContext, GucFlags, GucRegistry, GucSetting};
+
+use crate::Algorithm;
+
+/// Version of the canonical encoding of the `id_*` and `checksum_*` arguments.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PostgresGucEnum)]
+pub(crate) enum EncodingVersion {
+    /// Arguments joined by a `\0` byte, pairs sorted by key, pairs with the same key in argument
+    /// order.
+    #[name = c"1"]
+    V1,
+    /// Like `1`, but pairs with the same key sorted by value, so the order of the pairs never
+    /// changes the fingerprint.
+    #[name = c"2"]
+    V2,
+}
+
+static DEFAULT_ALGORITHM: GucSetting<Algorithm> = GucSetting::<Algorithm>::new(Algorithm::Farmhash);
+static ENCODING_VERSION: GucSetting<EncodingVersion> =
+    GucSetting::<EncodingVersion>::new(EncodingVersion::V1);
+static DEFINED: AtomicBool = AtomicBool::new(false);
+
+/// Define the settings of the algorithm-neutral `id_hash` and `checksum_hash` functions.
+///
+/// They can only be set at server start, so that these functions stay immutable. Such postmaster
+/// settings can only be defined while loading the library via `shared_preload_libraries`.
+pub(crate) fn init() {
+    if unsafe { !pg_sys::process_shared_preload_libraries_in_progress } {
+        return;
+    }
+
+    GucRegistry::define_enum_guc(
+        c"pg_row_hashes.default_algorithm",
+        c"Hash algorithm used by id_hash() and checksum_hash().",
+        c"Changing it changes every fingerprint calculated by these functions.",
+        &DEFAULT_ALGORITHM,
+        GucContext::Postmaster,
+        GucFlags::default(),
+    );
+    GucRegistry::define_enum_guc(
+        c"pg_row_hashes.encoding_version",
+        c"Encoding of the arguments used by id_hash() and checksum_hash().",
+        c"Changing it changes every fingerprint calculated by these functions.",
+        &ENCODING_VERSION,
+        GucContext::Postmaster,
+        GucFlags::default(),
+    );
+    DEFINED.store(true, Ordering::Relaxed);
+}
+
+pub(crate) fn default_algorithm() -> Algorithm {
+    require_defined();
+    DEFAULT_ALGORITHM.get()
+}
+
+pub(crate) fn encoding_version() -> EncodingVersion {
+    require_defined();
+    ENCODING_VERSION.get()
+}
+
+/// Without the settings, the functions using them would silently use the defaults.
+fn require_defined() {
+    if !DEFINED.load(Ordering::Relaxed) {
+        error!("id_hash() and checksum_hash() require pg_row_hashes in shared_preload_libraries");
+    }
+}
@@ -0,0 +1,335 @@
+//! HyperLogLog sketches over fingerprints.
+//!
+//! Like `bloom_agg()`, `hll_agg()` updates a single sketch in place in an `internal` transition
+//! state, so it is defined in SQL below instead of with `#[pg_aggregate]`.
+
+use pgrx::{prelude::*, Internal, Uuid};
+use serde::{Deserialize, Serialize};
+
+use crate::aggregate_state;
+
+extension_sql!(
+    r#"
+CREATE AGGREGATE hll_agg(bigint) (
+    SFUNC = hll_agg_bigint_state,
+    STYPE = internal,
+    FINALFUNC = hll_agg_finalize,
+    COMBINEFUNC = hll_agg_combine,
+    SERIALFUNC = hll_agg_serialize,
+    DESERIALFUNC = hll_agg_deserialize,
+    PARALLEL = SAFE
+);
+CREATE AGGREGATE hll_agg(uuid) (
+    SFUNC = hll_agg_uuid_state,
+    STYPE = internal,
+    FINALFUNC = hll_agg_finalize,
+    COMBINEFUNC = hll_agg_combine,
+    SERIALFUNC = hll_agg_serialize,
+    DESERIALFUNC = hll_agg_deserialize,
+    PARALLEL = SAFE
+);
+"#,
+    name = "hll_agg",
+    requires = [
+        hll_agg_bigint_state,
+        hll_agg_uuid_state,
+        hll_agg_finalize,
+        hll_agg_combine,
+        hll_agg_serialize,
+        hll_agg_deserialize,
+    ],
+);
+
+/// Number of index bits, i.e. 4096 registers with a standard error of 1.6%.
+const PRECISION: u8 = 12;
+
+/// Precisions of sketches read from their input.
+const PRECISIONS: std::ops::RangeInclusive<u8> = 4..=18;
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Debug, PartialEq, Eq, PostgresType, Serialize, Deserialize)]
+#[serde(from = "HllParts")]
+#[pg_binary_protocol]
+/// HyperLogLog sketch over fingerprints.
+///
+/// Fingerprints are uniformly distributed, so they are used as hash without rehashing. Empty
+/// `registers` are all zero, they are allocated by the first value.
+pub struct hll {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+/// Fields of a sketch as read from its text or binary input, before they are checked.
+#[derive(Deserialize)]
+struct HllParts {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl From<HllParts> for hll {
+    fn from(
+        HllParts {
+            precision,
+            registers,
+        }: HllParts,
+    ) -> Self {
+        hll::new(precision, registers)
+    }
+}
+
+impl Default for hll {
+    fn default() -> Self {
+        hll {
+            precision: PRECISION,
+            registers: Vec::new(),
+        }
+    }
+}
+
+impl hll {
+    /// A sketch read from its input, raising an error if it can't be used.
+    fn new(precision: u8, registers: Vec<u8>) -> Self {
+        if !PRECISIONS.contains(&precision) {
+            error!(
+                "hll precision must be between {} and {}, got {precision}",
+                PRECISIONS.start(),
+                PRECISIONS.end()
+            );
+        }
+        if !registers.is_empty() && registers.len() != 1 << precision {
+            error!(
+                "hll sketch of precision {precision} can't have {} registers",
+                registers.len()
+            );
+        }
+        hll {
+            precision,
+            registers,
+        }
+    }
+
+    fn insert(&mut self, hash: u64) {
+        let p = self.precision;
+        if self.registers.is_empty() {
+            self.registers = vec![0; 1 << p];
+        }
+        let index = (hash >> (64 - p)) as usize;
+        // the sentinel bit bounds the rank if all remaining bits are zero
+        let rank = ((hash << p) | (1 << (p - 1))).leading_zeros() as u8 + 1;
+        self.registers[index] = self.registers[index].max(rank);
+    }
+
+    /// Add the values of `other`, a sketch of the same precision.
+    fn merge(&mut self, other: &Self) {
+        if self.precision != other.precision {
+            error!("cannot combine hll sketches of different precision");
+        }
+        if self.registers.is_empty() {
+            self.registers.clone_from(&other.registers);
+            return;
+        }
+        for (register, other) in self.registers.iter_mut().zip(&other.registers) {
+            *register = (*register).max(*other);
+        }
+    }
+
+    fn union(mut self, other: Self) -> Self {
+        self.merge(&other);
+        self
+    }
+
+    /// The precision followed by the registers.
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + self.registers.len());
+        bytes.push(self.precision);
+        bytes.extend_from_slice(&self.registers);
+        bytes
+    }
+
+    /// The sketch of `to_bytes()`.
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let Some((&precision, registers)) = bytes.split_first() else {
+            error!("invalid hll sketch of 0 bytes");
+        };
+        hll::new(precision, registers.to_vec())
+    }
+
+    fn cardinality(&self) -> f64 {
+        if self.registers.is_empty() {
+            return 0.0;
+        }
+        let m = self.registers.len() as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self.registers.iter().map(|&r| (-f64::from(r)).exp2()).sum();
+        let estimate = alpha * m * m / sum;
+
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        if estimate <= 2.5 * m && zeros > 0 {
+            // linear counting is more accurate for small cardinalities
+            m * (m / zeros as f64).ln()
+        } else {
+            estimate
+        }
+    }
+}
+
+fn uuid_hash(value: &Uuid) -> u64 {
+    u128::from_le_bytes(*value.as_bytes()) as u64
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Estimated number of distinct fingerprints in the sketch.
+pub fn hll_cardinality(sketch: hll) -> i64 {
+    sketch.cardinality().round() as i64
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Union of two hll sketches.
+pub fn hll_union(a: hll, b: hll) -> hll {
+    a.union(b)
+}
+
+#[pg_extern(immutable, parallel_safe, create_or_replace)]
+/// Transition function of `hll_agg(bigint)`.
+pub fn hll_agg_bigint_state(
+    mut state: Internal,
+    value: Option<i64>,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> Internal {
+    if let Some(value) = value {
+        unsafe { aggregate_state(fcinfo, &mut state, hll::default) }.insert(value as u64);
+    }
+    state
+}
+
+#[pg_extern(immutable, parallel_safe, create_or_replace)]
+/// Transition function of `hll_agg(uuid)`.
+pub fn hll_agg_uuid_state(
+    mut state: Internal,
+    value: Option<Uuid>,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> Internal {
+    if let Some(value) = value {
+        unsafe { aggregate_state(fcinfo, &mut state, hll::default) }.insert(uuid_hash(&value));
+    }
+    state
+}
+
+#[pg_extern(immutable, parallel_safe, create_or_replace)]
+/// Final function of `hll_agg()`, an empty sketch if there were no values.
+pub fn hll_agg_finalize(state: Internal) -> hll {
+    unsafe { state.get::<hll>() }.cloned().unwrap_or_default()
+}
+
+#[pg_extern(immutable, parallel_safe, create_or_replace)]
+/// Combine function of `hll_agg()`.
+pub fn hll_agg_combine(
+    mut state: Internal,
+    other: Internal,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> Internal {
+    if let Some(other) = unsafe { other.get::<hll>() } {
+        unsafe { aggregate_state(fcinfo, &mut state, hll::default) }.merge(other);
+    }
+    state
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Serialization function of `hll_agg()`, to pass the state between parallel workers.
+pub fn hll_agg_serialize(state: Internal) -> Vec<u8> {
+    unsafe { state.get::<hll>() }
+        .expect("the state is not NULL")
+        .to_bytes()
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Deserialization function of `hll_agg()`.
+pub fn hll_agg_deserialize(
+    bytes: &[u8],
+    _state: Internal,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> Internal {
+    let mut state = Internal::from(None);
+    unsafe { aggregate_state(fcinfo, &mut state, || hll::from_bytes(bytes)) };
+    state
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pg_schema]
+mod tests {
+    use pgrx::prelude::*;
+
+    use pg_row_hashes_fingerprint::splitmix64;
+
+    use super::hll;
+
+    #[test]
+    fn test_hll_cardinality() {
+        for n in [0, 10, 1000, 100_000] {
+            let mut sketch = hll::default();
+            for i in 0..n {
+                // every value twice
+                sketch.insert(splitmix64(i));
+                sketch.insert(splitmix64(i));
+            }
+            let error = (sketch.cardinality() - n as f64).abs() / (n as f64).max(1.0);
+            assert!(error < 0.05, "estimated {} for {n}", sketch.cardinality());
+        }
+    }
+
+    #[test]
+    fn test_hll_union() {
+        let mut a = hll::default();
+        let mut b = hll::default();
+        for i in 0..10_000 {
+            a.insert(splitmix64(i));
+            b.insert(splitmix64(i + 5_000));
+        }
+        let union = a.union(b);
+        assert!((union.cardinality() - 15_000.0).abs() < 750.0);
+        assert_eq!(hll::default().union(union.clone()), union);
+        assert_eq!(hll::from_bytes(&union.to_bytes()), union);
+    }
+
+    #[pg_test]
+    fn pg_test_hll_agg() {
+        for (query, golden) in [
+            (
+                "SELECT hll_cardinality(hll_agg(id_seahash((i % 1000)::text))) FROM generate_series(1, 10000) i;",
+                1000,
+            ),
+            (
+                "SELECT hll_cardinality(hll_agg(id_farmhash((i % 1000)::text))) FROM generate_series(1, 10000) i;",
+                1000,
+            ),
+            (
+                "SELECT hll_cardinality(hll_union(
+                    (SELECT hll_agg(id_seahash(i::text)) FROM generate_series(1, 600) i),
+                    (SELECT hll_agg(id_seahash(i::text)) FROM generate_series(401, 1000) i)
+                 ));",
+                1000,
+            ),
+        ] {
+            let result = Spi::get_one::<i64>(query)
+                .expect("SPI failed")
+                .expect("got NULL");
+            assert!((result - golden).abs() < 50, "estimated {result} using {query}");
+        }
+        assert_eq!(
+            Spi::get_one::<i64>("SELECT hll_cardinality(hll_agg(1::bigint)) WHERE false;"),
+            Ok(Some(0))
+        );
+    }
+
+    #[pg_test(error = "hll precision must be between 4 and 18, got 0")]
+    fn pg_test_hll_input_precision() {
+        Spi::run(r#"SELECT hll_cardinality('{"precision":0,"registers":[]}'::hll);"#)
+            .expect("SPI failed");
+    }
+
+    #[pg_test(error = "hll sketch of precision 4 can't have 2 registers")]
+    fn pg_test_hll_input_registers() {
+        Spi::run(r#"SELECT hll_cardinality('{"precision":4,"registers":[1,2]}'::hll);"#)
+            .expect("SPI failed");
+    }
+}
@@ -1,16 +1,57 @@
+/// V1 calling convention info of functions taking raw datums, which are defined in SQL with
+/// `extension_sql!` instead of with `#[pg_extern]`.
+macro_rules! pg_finfo_v1 {
+    ($($finfo:ident),*) => {
+        $(
+            #[doc(hidden)]
+            #[unsafe(no_mangle)]
+            pub extern "C" fn $finfo() -> &'static ::pgrx::pg_sys::Pg_finfo_record {
+                const V1: ::pgrx::pg_sys::Pg_finfo_record =
+                    ::pgrx::pg_sys::Pg_finfo_record { api_version: 1 };
+                &V1
+            }
+        )*
+    };
+}
+
 mod array_functions;
+#[cfg(any(test, feature = "pg_test"))]
+mod bench;
+mod bgworker;
+mod bloom;
+mod chunks;
+mod convert;
+mod diff;
+mod guc;
+mod hll;
+mod minhash;
+mod opclass;
+mod record;
+mod sample;
+mod shard;
+mod simhash;
+mod snapshot;
+mod stream;
+mod trigger;
+mod types;
 mod xor_agg;
 
-use std::cmp::Ordering;
+use std::str::FromStr;
 
-use bytes::{BufMut, Bytes, BytesMut};
-use fasthash::farm;
-use itertools::Itertools;
-use pgrx::{prelude::*, Uuid, VariadicArray};
+use bytes::Bytes;
+use pg_row_hashes_fingerprint::{
+    checksum_fingerprint, checksum_fingerprint_v2, id_fingerprint, Algorithm as _, Farmhash,
+    NullPolicy, Seahash,
+};
+use pgrx::{prelude::*, spi, Internal, PgMemoryContexts, Uuid, VariadicArray};
 
 pgrx::pg_module_magic!();
 
-static ZERO_BYTE_ARRAY: [u8; 1] = [0];
+#[pg_guard]
+pub extern "C-unwind" fn _PG_init() {
+    guc::init();
+    bgworker::init();
+}
 
 #[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
 /// Hash a variadic array of strings into a md5 using md5 with _ as separator.
@@ -28,8 +69,8 @@ pub fn id_underscore_md5(a: VariadicArray<String>) -> Uuid {
     name = "id_farmhash"
 )]
 /// Hash a variadic array of strings into a Uuid using farmhash's fingerprint128
-pub fn id_farmhash_bytea(bytes: Vec<u8>) -> Uuid {
-    farmhash_fingerprint(bytes.into())
+pub fn id_farmhash_bytea(bytes: &[u8]) -> Uuid {
+    farmhash_uuid(Farmhash::fingerprint(bytes))
 }
 
 #[pg_extern(
@@ -40,378 +81,421 @@ pub fn id_farmhash_bytea(bytes: Vec<u8>) -> Uuid {
     name = "id_seahash"
 )]
 /// Hash a variadic array of strings into a Uuid using farmhash's fingerprint128
-pub fn id_seahash_bytea(bytes: Vec<u8>) -> i64 {
-    seahash_fingerprint(bytes.into())
+pub fn id_seahash_bytea(bytes: &[u8]) -> i64 {
+    seahash_bigint(Seahash::fingerprint(bytes))
 }
 
 #[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
 /// Hash a variadic array of strings into a Uuid using farmhash's fingerprint128
-pub fn id_farmhash(a: VariadicArray<String>) -> Uuid {
-    farmhash_fingerprint(ids_to_bytes(a))
+pub fn id_farmhash(a: VariadicArray<&str>) -> Uuid {
+    farmhash_uuid(id_fingerprint::<Farmhash, _>(a.iter_deny_null()))
 }
 
 #[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
 /// Hash a variadic array of strings into a bigint using seahash
-pub fn id_seahash(a: VariadicArray<String>) -> i64 {
-    seahash_fingerprint(ids_to_bytes(a))
+pub fn id_seahash(a: VariadicArray<&str>) -> i64 {
+    seahash_bigint(id_fingerprint::<Seahash, _>(a.iter_deny_null()))
 }
 
 #[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
 /// Hash a variadic array of pairs key, value of strings into a Uuid using farmhash's fingerprint128
-pub fn checksum_farmhash(a: VariadicArray<String>) -> Uuid {
+pub fn checksum_farmhash(a: VariadicArray<&str>) -> Uuid {
     assert!(a.len().is_multiple_of(2));
-    let b = normalized_pairs_bytes(a.iter(), filter_and_join_tuple_keep_null_values);
-    farmhash_fingerprint(b)
+    farmhash_uuid(checksum_fingerprint::<Farmhash, _>(a.iter(), NullPolicy::Keep))
 }
 
 #[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
 /// Hash a variadic array of pairs key, value of strings into a Uuid using farmhash's fingerprint128, skipping NULL values
-pub fn checksum_farmhash_extendable(a: VariadicArray<String>) -> Uuid {
+pub fn checksum_farmhash_extendable(a: VariadicArray<&str>) -> Uuid {
     assert!(a.len().is_multiple_of(2));
-    let b = normalized_pairs_bytes(a.iter(), filter_and_join_tuple);
-    farmhash_fingerprint(b)
+    farmhash_uuid(checksum_fingerprint::<Farmhash, _>(a.iter(), NullPolicy::Skip))
 }
 
 #[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
 /// Hash a variadic array of pairs key, value of strings into a Uuid using seahash.
-pub fn checksum_seahash(a: VariadicArray<String>) -> i64 {
+pub fn checksum_seahash(a: VariadicArray<&str>) -> i64 {
     assert!(a.len().is_multiple_of(2));
-    let b = normalized_pairs_bytes(a.iter(), filter_and_join_tuple_keep_null_values);
-    seahash_fingerprint(b)
+    seahash_bigint(checksum_fingerprint::<Seahash, _>(a.iter(), NullPolicy::Keep))
 }
 
 #[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
 /// Hash a variadic array of pairs key, value of strings into a Uuid using seahash, skipping NULL values.
-pub fn checksum_seahash_extendable(a: VariadicArray<String>) -> i64 {
+pub fn checksum_seahash_extendable(a: VariadicArray<&str>) -> i64 {
     assert!(a.len().is_multiple_of(2));
-    let b = normalized_pairs_bytes(a.iter(), filter_and_join_tuple);
-    seahash_fingerprint(b)
+    seahash_bigint(checksum_fingerprint::<Seahash, _>(a.iter(), NullPolicy::Skip))
 }
 
-#[inline]
-fn seahash_fingerprint(a: Bytes) -> i64 {
-    let digest = seahash::hash(&a);
+/// Name of the schema the extension is installed in, to reference its objects in SPI queries.
+pub(crate) fn extension_schema() -> Result<String, spi::Error> {
+    Spi::get_one::<String>(
+        "SELECT n.nspname::text FROM pg_extension e
+            JOIN pg_namespace n ON n.oid = e.extnamespace
+            WHERE e.extname = 'pg_row_hashes'",
+    )
+    .map(|schema| schema.unwrap_or_else(|| error!("extension pg_row_hashes is not installed")))
+}
 
-    // reversible u64 -> i64 by bytecasting
-    i64::from_ne_bytes(digest.to_ne_bytes())
+/// Hash algorithms that can be selected at runtime, e.g. via trigger arguments.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PostgresGucEnum)]
+pub(crate) enum Algorithm {
+    #[default]
+    #[name = c"farmhash"]
+    Farmhash,
+    #[name = c"seahash"]
+    Seahash,
 }
 
-#[inline]
-fn farmhash_fingerprint(a: Bytes) -> Uuid {
-    let digest = farm::fingerprint128(a);
-    Uuid::from_bytes(digest.to_le_bytes())
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "farmhash" => Ok(Algorithm::Farmhash),
+            "seahash" => Ok(Algorithm::Seahash),
+            _ => Err(format!(
+                "unknown algorithm \"{s}\", expected farmhash or seahash"
+            )),
+        }
+    }
 }
 
-#[inline]
-fn ids_to_bytes(a: VariadicArray<String>) -> Bytes {
-    id_iter_to_bytes(a.iter_deny_null())
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Hash a variadic array of strings using the algorithm in pg_row_hashes.default_algorithm, as bytea
+pub fn id_hash(a: VariadicArray<&str>) -> Vec<u8> {
+    id_hash_with(guc::default_algorithm(), guc::encoding_version(), || {
+        a.iter_deny_null()
+    })
 }
 
-#[inline]
-fn id_iter_to_bytes(a: impl Iterator<Item = String>) -> Bytes {
-    #[allow(unstable_name_collisions)] // silence warning about intersperse
-    let vec: Vec<Bytes> = a
-        .map(Bytes::from)
-        .intersperse(Bytes::from_static(&ZERO_BYTE_ARRAY))
-        .collect();
-    vec.concat().into()
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Hash a variadic array of pairs key, value of strings using the algorithm in pg_row_hashes.default_algorithm, as bytea
+pub fn checksum_hash(a: VariadicArray<&str>) -> Vec<u8> {
+    assert!(a.len().is_multiple_of(2));
+    checksum_hash_with(
+        guc::default_algorithm(),
+        guc::encoding_version(),
+        NullPolicy::Keep,
+        || a.iter(),
+    )
 }
 
-#[inline]
-fn join_tuple(a: Bytes, b: Bytes) -> Bytes {
-    let mut buf = BytesMut::with_capacity(a.len() + b.len() + 1);
-    buf.put(a);
-    buf.put_u8(0);
-    buf.put(b);
-    buf.freeze()
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Hash a variadic array of pairs key, value of strings using the algorithm in pg_row_hashes.default_algorithm, skipping NULL values, as bytea
+pub fn checksum_hash_extendable(a: VariadicArray<&str>) -> Vec<u8> {
+    assert!(a.len().is_multiple_of(2));
+    checksum_hash_with(
+        guc::default_algorithm(),
+        guc::encoding_version(),
+        NullPolicy::Skip,
+        || a.iter(),
+    )
 }
 
-#[inline]
-fn filter_and_join_tuple((a, b): (Option<Bytes>, Option<Bytes>)) -> Option<Bytes> {
-    if let (Some(a), Some(b)) = (a, b) {
-        Some(join_tuple(a, b))
-    } else {
-        None
+/// `id_hash` with the given settings instead of the server's.
+fn id_hash_with<I, T>(
+    algorithm: Algorithm,
+    version: guc::EncodingVersion,
+    ids: impl Fn() -> I,
+) -> Vec<u8>
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<[u8]>,
+{
+    // ids are encoded the same by every version
+    match version {
+        guc::EncodingVersion::V1 | guc::EncodingVersion::V2 => fingerprint_bytes(
+            algorithm,
+            || id_fingerprint::<Farmhash, _>(ids()),
+            || id_fingerprint::<Seahash, _>(ids()),
+        ),
     }
 }
 
-#[inline]
-fn filter_and_join_tuple_keep_null_values((a, b): (Option<Bytes>, Option<Bytes>)) -> Option<Bytes> {
-    match (a, b) {
-        (Some(a), Some(b)) => Some(join_tuple(a, b)),
-        (Some(a), _) => Some(join_tuple(a, Bytes::new())),
-        _ => None,
+/// `checksum_hash` or `checksum_hash_extendable` with the given settings instead of the server's.
+fn checksum_hash_with<I, T>(
+    algorithm: Algorithm,
+    version: guc::EncodingVersion,
+    nulls: NullPolicy,
+    pairs: impl Fn() -> I,
+) -> Vec<u8>
+where
+    I: IntoIterator<Item = Option<T>>,
+    T: AsRef<[u8]>,
+{
+    match version {
+        guc::EncodingVersion::V1 => fingerprint_bytes(
+            algorithm,
+            || checksum_fingerprint::<Farmhash, _>(pairs(), nulls),
+            || checksum_fingerprint::<Seahash, _>(pairs(), nulls),
+        ),
+        guc::EncodingVersion::V2 => fingerprint_bytes(
+            algorithm,
+            || checksum_fingerprint_v2::<Farmhash, _>(pairs(), nulls),
+            || checksum_fingerprint_v2::<Seahash, _>(pairs(), nulls),
+        ),
     }
 }
 
+/// Binary representation of the fingerprint, the same as `uuid_send` or `int8send` of the typed result.
 #[inline]
-fn normalized_pairs_bytes<T, F>(a: T, f: F) -> Bytes
-where
-    T: Iterator<Item = Option<String>>,
-    F: FnMut((Option<Bytes>, Option<Bytes>)) -> Option<Bytes>,
-{
-    let mut vec: Vec<_> = a.map(|e| e.map(Bytes::from)).tuples().collect();
+fn fingerprint_bytes(
+    algorithm: Algorithm,
+    farmhash: impl FnOnce() -> u128,
+    seahash: impl FnOnce() -> u64,
+) -> Vec<u8> {
+    match algorithm {
+        Algorithm::Farmhash => farmhash_uuid(farmhash()).as_bytes().to_vec(),
+        Algorithm::Seahash => seahash_bigint(seahash()).to_be_bytes().to_vec(),
+    }
+}
 
-    vec.sort_unstable_by(cmp_option_tuple);
+#[inline]
+pub(crate) fn seahash_fingerprint(a: Bytes) -> i64 {
+    seahash_bigint(Seahash::fingerprint(&a))
+}
 
-    // filter out same key-value tuples
-    vec.dedup();
+#[inline]
+pub(crate) fn farmhash_fingerprint(a: Bytes) -> Uuid {
+    farmhash_uuid(Farmhash::fingerprint(&a))
+}
 
-    #[allow(unstable_name_collisions)] // silence warning about intersperse
-    let vec: Vec<_> = vec
-        .into_iter()
-        .flat_map(f)
-        .intersperse(Bytes::from_static(&ZERO_BYTE_ARRAY))
-        .collect();
-    vec.concat().into()
+#[inline]
+pub(crate) fn seahash_bigint(digest: u64) -> i64 {
+    // reversible u64 -> i64 by bytecasting
+    i64::from_ne_bytes(digest.to_ne_bytes())
 }
 
 #[inline]
-fn cmp_option_tuple<T, T_>(a: &(Option<T>, Option<T_>), b: &(Option<T>, Option<T_>)) -> Ordering
-where
-    T: Ord,
-{
-    match (a, b) {
-        ((Some(a), _), (Some(b), _)) => a.cmp(b),
-        ((Some(_), _), _) => Ordering::Greater,
-        (_, (Some(_), _)) => Ordering::Less,
-        (_, _) => Ordering::Equal,
+pub(crate) fn farmhash_uuid(digest: u128) -> Uuid {
+    Uuid::from_bytes(digest.to_le_bytes())
+}
+
+/// The `internal` transition state of an aggregate, created by `init` in the aggregate's memory
+/// context on the first call, so it is updated in place instead of copied for every row.
+pub(crate) unsafe fn aggregate_state<T>(
+    fcinfo: pg_sys::FunctionCallInfo,
+    state: &mut Internal,
+    init: impl FnOnce() -> T,
+) -> &mut T {
+    unsafe {
+        if state.get::<T>().is_none() {
+            let mut context = std::ptr::null_mut();
+            if pg_sys::AggCheckCallContext(fcinfo, &mut context) == 0 {
+                error!("aggregate function called in non-aggregate context");
+            }
+            let value = PgMemoryContexts::For(context).leak_and_drop_on_delete(init());
+            *state = Internal::from(Some(pg_sys::Datum::from(value)));
+        }
+        state.get_mut::<T>().expect("the state was created")
     }
 }
 
 #[cfg(any(test, feature = "pg_test"))]
 #[pg_schema]
 mod tests {
-    use bytes::Bytes;
-    use fasthash::farm;
+    use pg_row_hashes_fingerprint::golden::{CHECKSUM_GOLDEN_TABLE, ID_GOLDEN_TABLE};
+    use pg_row_hashes_fingerprint::NullPolicy;
     use pgrx::prelude::*;
     use pgrx::Uuid;
 
-    static CHECKSUM_GOLDEN_TABLE: [(&str, u128, u64, u128, u64); 7] = [
-        (
-            "'b','1','a','2','c','3'",
-            200872945134416140889070688363161139169,
-            2409718014940744358,
-            200872945134416140889070688363161139169,
-            2409718014940744358,
-        ),
-        (
-            "'c','1','b','2','a','3'",
-            159497434488907202746785405570285430728,
-            13904897432444956006,
-            159497434488907202746785405570285430728,
-            13904897432444956006,
-        ),
-        (
-            "'a','1','b','2','c','3'",
-            63991421267038837894546220157490372611,
-            2987942282093369026,
-            63991421267038837894546220157490372611,
-            2987942282093369026,
-        ),
-        (
-            "'c','3','b','2','a','1'",
-            63991421267038837894546220157490372611,
-            2987942282093369026,
-            63991421267038837894546220157490372611,
-            2987942282093369026,
-        ),
-        (
-            "'d',NULL,'b','1','a','2','c','3'",
-            200872945134416140889070688363161139169,
-            2409718014940744358,
-            81995647272375524016145587428893207932,
-            1483524512128736869,
-        ),
-        (
-            "'b','1','e,', NULL, 'a','2','c','3','d',NULL",
-            200872945134416140889070688363161139169,
-            2409718014940744358,
-            25535654360990964780919159173614512903,
-            2918039635165515565,
-        ),
-        (
-            "'a','1','d',NULL,'b','2','c','3'",
-            63991421267038837894546220157490372611,
-            2987942282093369026,
-            55229287721383068868671312639375865447,
-            13930034059081198257,
-        ),
-    ];
-
-    static ID_GOLDEN_TABLE: [(Bytes, &str, u128, u64); 4] = [
-        (
-            Bytes::from_static(b"a\0b\0c"),
-            "'a','b','c'",
-            185254626185375829619130502206294491400,
-            14302911629075895706,
-        ),
-        (
-            Bytes::from_static(b"a\0bc"),
-            "'a','bc'",
-            209923663634918632141264334831867734826,
-            11633404322457790885,
-        ),
-        (
-            Bytes::from_static(b"ab\0c"),
-            "'ab','c'",
-            48977107076008525069529680716651780466,
-            15405044104555301515,
-        ),
-        (
-            Bytes::from_static(b"ab\0c\0d"),
-            "'ab','c','d'",
-            236038958675336168148183549336891112308,
-            15729725979387502697,
-        ),
-    ];
+    use super::{checksum_hash_with, id_hash_with};
+    use crate::guc::EncodingVersion;
+    use crate::Algorithm;
+
+    /// Render values as SQL function arguments.
+    fn sql_args<'a>(values: impl IntoIterator<Item = Option<&'a str>>) -> String {
+        values
+            .into_iter()
+            .map(|v| v.map_or("NULL".to_string(), |v| format!("'{v}'")))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
 
-    #[test]
-    fn test_normalized_pairs_keeping_nulls() {
-        let table = vec![
-            (
-                vec!["a", "v", "b", "v2"],
-                Bytes::from_static(b"a\0v\0b\0v2"),
-            ),
-            (
-                vec!["b", "v2", "a", "v"],
-                Bytes::from_static(b"a\0v\0b\0v2"),
-            ),
-            (
-                vec!["c", "v3", "a", "v", "b", "v2"],
-                Bytes::from_static(b"a\0v\0b\0v2\0c\0v3"),
-            ),
-            (
-                vec!["b", "v2", "c", "v3", "a", "v"],
-                Bytes::from_static(b"a\0v\0b\0v2\0c\0v3"),
-            ),
-        ];
-        for (arr, bytes) in table.iter() {
-            let arr_iter = arr.iter().map(|s| s.to_string()).map(Option::Some);
-            let result = crate::normalized_pairs_bytes(
-                arr_iter,
-                crate::filter_and_join_tuple_keep_null_values,
-            );
-            assert_eq!(result, bytes, "using {:?}", arr);
+    #[pg_test]
+    fn pg_test_default_algorithm() {
+        for (_, ids, _, _) in ID_GOLDEN_TABLE.iter() {
+            let params = sql_args(ids.iter().copied().map(Some));
+            let (result, golden) = Spi::get_two::<Vec<u8>, Vec<u8>>(&format!(
+                "SELECT id_hash({params}), uuid_send(id_farmhash({params}));"
+            ))
+            .expect("didn't get SPI result");
+            assert_eq!(result, golden, "using {}", params);
         }
 
-        let table = vec![
-            (
-                vec![Some("c"), None, Some("a"), None, Some("b"), Some("v2")],
-                Bytes::from_static(b"a\0\0b\0v2\0c\0"),
-            ),
-            (
-                vec![
-                    Some("c"),
-                    Some("v3"),
-                    Some("a"),
-                    None,
-                    Some("b"),
-                    Some("v2"),
-                ],
-                Bytes::from_static(b"a\0\0b\0v2\0c\0v3"),
-            ),
-            (
-                vec![
-                    Some("d"),
-                    None,
-                    Some("b"),
-                    Some("1"),
-                    Some("a"),
-                    None,
-                    Some("c"),
-                    Some("3"),
-                ],
-                Bytes::from_static(b"a\0\0b\01\0c\03\0d\0"),
-            ),
-        ];
-        for (arr, bytes) in table.iter() {
-            let arr_iter = arr.iter().map(|s| s.map(|s| s.to_string()));
-            let result = crate::normalized_pairs_bytes(
-                arr_iter,
-                crate::filter_and_join_tuple_keep_null_values,
-            );
-            assert_eq!(result, bytes, "using {:?}", arr);
+        for (pairs, _, _, _, _) in CHECKSUM_GOLDEN_TABLE.iter() {
+            let params = sql_args(pairs.iter().copied());
+            let (result, golden) = Spi::get_two::<Vec<u8>, Vec<u8>>(&format!(
+                "SELECT checksum_hash({params}), uuid_send(checksum_farmhash({params}));"
+            ))
+            .expect("didn't get SPI result");
+            assert_eq!(result, golden, "using {}", params);
+
+            let (result, golden) = Spi::get_two::<Vec<u8>, Vec<u8>>(&format!(
+                "SELECT checksum_hash_extendable({params}), uuid_send(checksum_farmhash_extendable({params}));"
+            ))
+            .expect("didn't get SPI result");
+            assert_eq!(result, golden, "using {}", params);
         }
     }
 
+    #[pg_test(
+        error = "parameter \"pg_row_hashes.default_algorithm\" cannot be changed without restarting the server"
+    )]
+    fn pg_test_settings_are_fixed() {
+        Spi::run("SET pg_row_hashes.default_algorithm = seahash;").expect("SPI failed");
+    }
+
     #[test]
-    fn test_normalized_pairs() {
-        let table = vec![
-            (
-                vec!["a", "v", "b", "v2"],
-                Bytes::from_static(b"a\0v\0b\0v2"),
-            ),
-            (
-                vec!["b", "v2", "a", "v"],
-                Bytes::from_static(b"a\0v\0b\0v2"),
-            ),
-            (
-                vec!["c", "v3", "a", "v", "b", "v2"],
-                Bytes::from_static(b"a\0v\0b\0v2\0c\0v3"),
-            ),
-            (
-                vec!["b", "v2", "c", "v3", "a", "v"],
-                Bytes::from_static(b"a\0v\0b\0v2\0c\0v3"),
-            ),
-        ];
-        for (arr, bytes) in table.iter() {
-            let arr_iter = arr.iter().map(|s| s.to_string()).map(Option::Some);
-            let result = crate::normalized_pairs_bytes(arr_iter, crate::filter_and_join_tuple);
-            assert_eq!(result, bytes, "using {:?}", arr);
+    fn test_hash_with() {
+        for (_, ids, farmhash, seahash) in ID_GOLDEN_TABLE.iter() {
+            for version in [EncodingVersion::V1, EncodingVersion::V2] {
+                assert_eq!(
+                    id_hash_with(Algorithm::Farmhash, version, || ids.iter()),
+                    farmhash.to_le_bytes(),
+                    "using {ids:?}"
+                );
+                assert_eq!(
+                    id_hash_with(Algorithm::Seahash, version, || ids.iter()),
+                    seahash.to_be_bytes(),
+                    "using {ids:?}"
+                );
+            }
         }
 
-        let table = vec![
-            (
-                vec![Some("c"), Some("v3"), Some("a"), None, Some("b"), None],
-                Bytes::from_static(b"c\0v3"),
-            ),
-            (
-                vec![
-                    Some("c"),
-                    Some("v3"),
-                    Some("a"),
-                    None,
-                    Some("b"),
-                    Some("v2"),
-                ],
-                Bytes::from_static(b"b\0v2\0c\0v3"),
-            ),
-            (
-                vec![
-                    Some("d"),
-                    None,
-                    Some("b"),
-                    Some("1"),
-                    Some("a"),
-                    None,
-                    Some("c"),
-                    Some("3"),
-                ],
-                Bytes::from_static(b"b\01\0c\03"),
-            ),
-        ];
-        for (arr, bytes) in table.iter() {
-            let arr_iter = arr.iter().map(|s| s.map(|s| s.to_string()));
-            let result = crate::normalized_pairs_bytes(arr_iter, crate::filter_and_join_tuple);
-            assert_eq!(result, bytes, "using {:?}", arr);
+        // the golden pairs have distinct keys, so both versions encode them the same
+        for (pairs, farmhash_extendable, seahash_extendable, farmhash, seahash) in
+            CHECKSUM_GOLDEN_TABLE.iter()
+        {
+            for version in [EncodingVersion::V1, EncodingVersion::V2] {
+                for (algorithm, nulls, golden) in [
+                    (
+                        Algorithm::Farmhash,
+                        NullPolicy::Keep,
+                        farmhash.to_le_bytes().to_vec(),
+                    ),
+                    (
+                        Algorithm::Seahash,
+                        NullPolicy::Keep,
+                        seahash.to_be_bytes().to_vec(),
+                    ),
+                    (
+                        Algorithm::Farmhash,
+                        NullPolicy::Skip,
+                        farmhash_extendable.to_le_bytes().to_vec(),
+                    ),
+                    (
+                        Algorithm::Seahash,
+                        NullPolicy::Skip,
+                        seahash_extendable.to_be_bytes().to_vec(),
+                    ),
+                ] {
+                    assert_eq!(
+                        checksum_hash_with(algorithm, version, nulls, || pairs.iter().copied()),
+                        golden,
+                        "using {pairs:?} with {algorithm:?}, {version:?} and {nulls:?}"
+                    );
+                }
+            }
         }
     }
 
     #[test]
-    fn test_farmhash() {
-        for (bytes, params, golden, _) in ID_GOLDEN_TABLE.iter() {
-            let result = farm::fingerprint128(bytes);
-            assert_eq!(result, *golden, "using {:?}", params);
+    fn test_encoding_version() {
+        let pairs = [
+            Some("b"),
+            Some("2"),
+            Some("a"),
+            Some("1"),
+            Some("b"),
+            Some("1"),
+        ];
+        let reordered = [
+            Some("b"),
+            Some("1"),
+            Some("a"),
+            Some("1"),
+            Some("b"),
+            Some("2"),
+        ];
+        for algorithm in [Algorithm::Farmhash, Algorithm::Seahash] {
+            for (version, same) in [(EncodingVersion::V1, false), (EncodingVersion::V2, true)] {
+                assert_eq!(
+                    checksum_hash_with(algorithm, version, NullPolicy::Keep, || pairs)
+                        == checksum_hash_with(algorithm, version, NullPolicy::Keep, || reordered),
+                    same,
+                    "using {algorithm:?} and {version:?}"
+                );
+            }
         }
     }
 
-    #[test]
-    fn test_seahash() {
-        for (bytes, params, _, golden) in ID_GOLDEN_TABLE.iter() {
-            let result = seahash::hash(bytes);
-            assert_eq!(result, *golden, "using {}", params);
-        }
+    /// Load a corpus of the fingerprint crate (see `fingerprint/tests/corpus.rs`) into `table`.
+    fn load_corpus(table: &str, columns: &str, file: &str, cases: i64) {
+        let path = format!(
+            "{}/fingerprint/tests/corpus/{file}",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        Spi::run(&format!(
+            "CREATE TABLE {table} ({columns});
+             COPY {table} FROM '{path}' WITH (ENCODING 'UTF8');"
+        ))
+        .expect("SPI failed");
+
+        let count = Spi::get_one::<i64>(&format!("SELECT count(*) FROM {table};"))
+            .expect("didn't get SPI result")
+            .expect("got None");
+        assert!(count >= cases, "only {count} cases in {file}");
+    }
+
+    /// Assert that `mismatch` matches none of the rows of the corpus in `table`.
+    ///
+    /// The tests run with the default `pg_row_hashes.default_algorithm`, farmhash, which can't be
+    /// changed at runtime. `test_hash_with` covers seahash.
+    fn check_corpus(table: &str, mismatch: &str) {
+        let mismatches = Spi::get_one::<String>(&format!(
+            "SELECT string_agg(args::text, E'\\n') FROM
+                (SELECT * FROM {table} c WHERE {mismatch} LIMIT 10) m(args);"
+        ));
+        assert_eq!(mismatches, Ok(None), "in {table}");
+    }
+
+    #[pg_test]
+    fn pg_test_id_corpus() {
+        load_corpus(
+            "id_corpus",
+            "ids text[], id_farmhash uuid, id_seahash bigint",
+            "ids.tsv",
+            2000,
+        );
+        check_corpus(
+            "id_corpus",
+            "id_farmhash(VARIADIC ids) IS DISTINCT FROM c.id_farmhash
+                OR id_seahash(VARIADIC ids) IS DISTINCT FROM c.id_seahash
+                OR id_hash(VARIADIC ids) IS DISTINCT FROM uuid_send(c.id_farmhash)",
+        );
+    }
+
+    #[pg_test]
+    fn pg_test_checksum_corpus() {
+        load_corpus(
+            "checksum_corpus",
+            "pairs text[], checksum_farmhash uuid, checksum_seahash bigint,
+             checksum_farmhash_extendable uuid, checksum_seahash_extendable bigint",
+            "checksums.tsv",
+            3500,
+        );
+        check_corpus(
+            "checksum_corpus",
+            "checksum_farmhash(VARIADIC pairs) IS DISTINCT FROM c.checksum_farmhash
+                OR checksum_farmhash_extendable(VARIADIC pairs)
+                    IS DISTINCT FROM c.checksum_farmhash_extendable
+                OR checksum_seahash(VARIADIC pairs) IS DISTINCT FROM c.checksum_seahash
+                OR checksum_seahash_extendable(VARIADIC pairs)
+                    IS DISTINCT FROM c.checksum_seahash_extendable
+                OR checksum_hash(VARIADIC pairs) IS DISTINCT FROM uuid_send(c.checksum_farmhash)
+                OR checksum_hash_extendable(VARIADIC pairs)
+                    IS DISTINCT FROM uuid_send(c.checksum_farmhash_extendable)",
+        );
     }
 
     #[pg_test]
@@ -425,7 +509,8 @@ mod tests {
 
     #[pg_test]
     fn pg_test_id_farmhash() {
-        for (_, params, golden, _) in ID_GOLDEN_TABLE.iter() {
+        for (_, ids, golden, _) in ID_GOLDEN_TABLE.iter() {
+            let params = sql_args(ids.iter().copied().map(Some));
             let result = Spi::get_one::<Uuid>(&format!("SELECT id_farmhash({});", params))
                 .expect("didn't get SPI result")
                 .expect("got None");
@@ -436,7 +521,8 @@ mod tests {
 
     #[pg_test]
     fn pg_test_id_seahash() {
-        for (_, params, _, golden) in ID_GOLDEN_TABLE.iter() {
+        for (_, ids, _, golden) in ID_GOLDEN_TABLE.iter() {
+            let params = sql_args(ids.iter().copied().map(Some));
             let result = Spi::get_one::<i64>(&format!("SELECT id_seahash({});", params))
                 .expect("didn't get SPI result")
                 .expect("got None");
@@ -447,7 +533,8 @@ mod tests {
 
     #[pg_test]
     fn pg_test_checksum_farmhash() {
-        for (params, _, _, golden, _) in CHECKSUM_GOLDEN_TABLE.iter() {
+        for (pairs, _, _, golden, _) in CHECKSUM_GOLDEN_TABLE.iter() {
+            let params = sql_args(pairs.iter().copied());
             let result = Spi::get_one::<Uuid>(&format!("SELECT checksum_farmhash({});", params))
                 .expect("didn't get SPI result")
                 .expect("got None");
@@ -463,7 +550,8 @@ mod tests {
 
     #[pg_test]
     fn pg_test_checksum_seahash() {
-        for (params, _, _, _, golden) in CHECKSUM_GOLDEN_TABLE.iter() {
+        for (pairs, _, _, _, golden) in CHECKSUM_GOLDEN_TABLE.iter() {
+            let params = sql_args(pairs.iter().copied());
             let result = Spi::get_one::<i64>(&format!("SELECT checksum_seahash({});", params))
                 .expect("didn't get SPI result")
                 .expect("got None");
@@ -478,7 +566,8 @@ mod tests {
     }
     #[pg_test]
     fn pg_test_checksum_farmhash_extendable() {
-        for (params, golden, _, _, _) in CHECKSUM_GOLDEN_TABLE.iter() {
+        for (pairs, golden, _, _, _) in CHECKSUM_GOLDEN_TABLE.iter() {
+            let params = sql_args(pairs.iter().copied());
             let result =
                 Spi::get_one::<Uuid>(&format!("SELECT checksum_farmhash_extendable({});", params))
                     .expect("didn't get SPI result")
@@ -495,7 +584,8 @@ mod tests {
 
     #[pg_test]
     fn pg_test_checksum_seahash_extendable() {
-        for (params, _, golden, _, _) in CHECKSUM_GOLDEN_TABLE.iter() {
+        for (pairs, _, golden, _, _) in CHECKSUM_GOLDEN_TABLE.iter() {
+            let params = sql_args(pairs.iter().copied());
             let result =
                 Spi::get_one::<i64>(&format!("SELECT checksum_seahash_extendable({});", params))
                     .expect("didn't get SPI result")
@@ -520,7 +610,7 @@ pub mod pg_test {
     }
 
     pub fn postgresql_conf_options() -> Vec<&'static str> {
-        // return any postgresql.conf settings that are required for your tests
-        vec![]
+        // id_hash() and checksum_hash() require their settings, which are defined while preloading
+        vec!["shared_preload_libraries = 'pg_row_hashes'"]
     }
 }
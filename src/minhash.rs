@@ -0,0 +1,286 @@
+//! MinHash signatures of sets of key, value pairs or values.
+//!
+//! Like `bloom_agg()`, `minhash_agg()` updates a single signature in place in an `internal`
+//! transition state, so it is defined in SQL below instead of with `#[pg_aggregate]`.
+
+use pg_row_hashes_fingerprint::{pair_bytes, splitmix64, Algorithm as _, Farmhash, NullPolicy};
+use pgrx::{prelude::*, Internal, VariadicArray};
+
+use crate::aggregate_state;
+
+extension_sql!(
+    r#"
+CREATE AGGREGATE minhash_agg(value text, k integer) (
+    SFUNC = minhash_agg_state,
+    STYPE = internal,
+    FINALFUNC = minhash_agg_finalize,
+    COMBINEFUNC = minhash_agg_combine,
+    SERIALFUNC = minhash_agg_serialize,
+    DESERIALFUNC = minhash_agg_deserialize,
+    PARALLEL = SAFE
+);
+"#,
+    name = "minhash_agg",
+    requires = [
+        minhash_agg_state,
+        minhash_agg_finalize,
+        minhash_agg_combine,
+        minhash_agg_serialize,
+        minhash_agg_deserialize,
+    ],
+);
+
+/// Smallest hash per hash function, the `i`th function derived from an element's fingerprint128
+/// by double hashing.
+///
+/// The default value without hash functions is the state of `minhash_agg()` before any value.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Signature(Vec<u64>);
+
+impl Signature {
+    fn new(k: i32) -> Self {
+        if k <= 0 {
+            error!("minhash signature size must be positive, got {k}");
+        }
+        Signature(vec![u64::MAX; k as usize])
+    }
+
+    fn insert(&mut self, element: &[u8]) {
+        let h = Farmhash::fingerprint(element);
+        let (h1, h2) = (h as u64, (h >> 64) as u64 | 1);
+        for (i, min) in (0u64..).zip(self.0.iter_mut()) {
+            *min = (*min).min(splitmix64(h1.wrapping_add(i.wrapping_mul(h2))));
+        }
+    }
+
+    /// Add the elements of `other`, a signature of the same size.
+    fn merge(&mut self, other: &Self) {
+        if other.0.is_empty() {
+            return;
+        }
+        if self.0.is_empty() {
+            *self = other.clone();
+            return;
+        }
+        if self.0.len() != other.0.len() {
+            error!(
+                "cannot combine minhash signatures of size {} and {}",
+                self.0.len(),
+                other.0.len()
+            );
+        }
+        for (min, other) in self.0.iter_mut().zip(&other.0) {
+            *min = (*min).min(*other);
+        }
+    }
+
+    /// Signature of the union of both sets.
+    fn union(mut self, other: &Self) -> Self {
+        self.merge(other);
+        self
+    }
+
+    fn similarity(&self, other: &Self) -> f64 {
+        let equal = self.0.iter().zip(&other.0).filter(|(a, b)| a == b).count();
+        equal as f64 / self.0.len() as f64
+    }
+
+    fn from_sql(signature: Vec<i64>) -> Self {
+        Signature(signature.into_iter().map(|h| h as u64).collect())
+    }
+
+    fn into_sql(self) -> Vec<i64> {
+        self.0.into_iter().map(|h| h as i64).collect()
+    }
+
+    /// The hashes in little endian order.
+    fn to_bytes(&self) -> Vec<u8> {
+        self.0.iter().flat_map(|h| h.to_le_bytes()).collect()
+    }
+
+    /// The signature of `to_bytes()`.
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Signature(
+            bytes
+                .chunks_exact(8)
+                .map(|h| u64::from_le_bytes(h.try_into().expect("hashes have 8 bytes")))
+                .collect(),
+        )
+    }
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// MinHash signature of size `k` of the set of key, value pairs, as encoded by `checksum_farmhash`.
+pub fn minhash_signature(k: i32, a: VariadicArray<String>) -> Vec<i64> {
+    assert!(a.len().is_multiple_of(2));
+    let mut signature = Signature::new(k);
+    for pair in pair_bytes(a.iter(), NullPolicy::Keep) {
+        signature.insert(&pair);
+    }
+    signature.into_sql()
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Estimated Jaccard similarity of the sets of two MinHash signatures of the same size.
+pub fn minhash_similarity(a: Vec<i64>, b: Vec<i64>) -> f64 {
+    if a.len() != b.len() || a.is_empty() {
+        error!(
+            "cannot compare minhash signatures of size {} and {}",
+            a.len(),
+            b.len()
+        );
+    }
+    Signature::from_sql(a).similarity(&Signature::from_sql(b))
+}
+
+#[pg_extern(immutable, parallel_safe, create_or_replace)]
+/// Transition function of `minhash_agg()`, sizing the signature by the first value.
+pub fn minhash_agg_state(
+    mut state: Internal,
+    value: Option<&str>,
+    k: Option<i32>,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> Internal {
+    if let (Some(value), Some(k)) = (value, k) {
+        let signature = unsafe { aggregate_state(fcinfo, &mut state, || Signature::new(k)) };
+        if signature.0.len() as i64 != i64::from(k) {
+            error!(
+                "minhash signature size changed from {} to {k}",
+                signature.0.len()
+            );
+        }
+        signature.insert(value.as_bytes());
+    }
+    state
+}
+
+#[pg_extern(immutable, parallel_safe, create_or_replace)]
+/// Final function of `minhash_agg()`, an empty signature if there were no values.
+pub fn minhash_agg_finalize(state: Internal) -> Vec<i64> {
+    unsafe { state.get::<Signature>() }
+        .cloned()
+        .unwrap_or_default()
+        .into_sql()
+}
+
+#[pg_extern(immutable, parallel_safe, create_or_replace)]
+/// Combine function of `minhash_agg()`.
+pub fn minhash_agg_combine(
+    mut state: Internal,
+    other: Internal,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> Internal {
+    if let Some(other) = unsafe { other.get::<Signature>() } {
+        unsafe { aggregate_state(fcinfo, &mut state, Signature::default) }.merge(other);
+    }
+    state
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Serialization function of `minhash_agg()`, to pass the state between parallel workers.
+pub fn minhash_agg_serialize(state: Internal) -> Vec<u8> {
+    unsafe { state.get::<Signature>() }
+        .expect("the state is not NULL")
+        .to_bytes()
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Deserialization function of `minhash_agg()`.
+pub fn minhash_agg_deserialize(
+    bytes: &[u8],
+    _state: Internal,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> Internal {
+    let mut state = Internal::from(None);
+    unsafe { aggregate_state(fcinfo, &mut state, || Signature::from_bytes(bytes)) };
+    state
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pg_schema]
+mod tests {
+    use pgrx::prelude::*;
+
+    use super::Signature;
+
+    fn signature(elements: impl IntoIterator<Item = u32>) -> Signature {
+        let mut signature = Signature::new(256);
+        for e in elements {
+            signature.insert(e.to_string().as_bytes());
+        }
+        signature
+    }
+
+    #[test]
+    fn test_minhash_similarity() {
+        // Jaccard similarity of 0..100 and 50..150 is 50 / 150
+        let a = signature(0..100);
+        let b = signature(50..150);
+        assert!((a.similarity(&b) - 1.0 / 3.0).abs() < 0.1);
+        assert_eq!(a.similarity(&signature((0..100).rev())), 1.0);
+        assert!(a.similarity(&signature(1000..1100)) < 0.05);
+    }
+
+    #[test]
+    fn test_minhash_union() {
+        let union = signature(0..50).union(&signature(50..100));
+        assert_eq!(union, signature(0..100));
+        assert_eq!(Signature::default().union(&union), union);
+    }
+
+    #[test]
+    fn test_minhash_bytes() {
+        let signature = signature(0..10);
+        assert_eq!(Signature::from_bytes(&signature.to_bytes()), signature);
+    }
+
+    #[pg_test]
+    fn pg_test_minhash() {
+        for (query, golden) in [
+            (
+                "SELECT minhash_similarity(
+                    minhash_signature(64, 'name', 'Jane', 'city', 'Berlin', 'zip', '10115'),
+                    minhash_signature(64, 'zip', '10115', 'name', 'Jane', 'city', 'Berlin'));",
+                1.0,
+            ),
+            (
+                "SELECT minhash_similarity(
+                    minhash_signature(64, 'name', 'Jane', 'city', 'Berlin'),
+                    minhash_signature(64, 'email', 'x@example.com', 'phone', '123'));",
+                0.0,
+            ),
+            (
+                "SELECT minhash_similarity(
+                    (SELECT minhash_agg(i::text, 64) FROM generate_series(1, 100) i),
+                    (SELECT minhash_agg(i::text, 64) FROM generate_series(100, 1, -1) i));",
+                1.0,
+            ),
+        ] {
+            let result = Spi::get_one::<f64>(query);
+            assert_eq!(result, Ok(Some(golden)), "using {query}");
+        }
+
+        let similarity = Spi::get_one::<f64>(
+            "SELECT minhash_similarity(
+                minhash_signature(256, 'a', '1', 'b', '2', 'c', '3', 'd', '4'),
+                minhash_signature(256, 'a', '1', 'b', '2', 'c', '3', 'd', '5'));",
+        )
+        .expect("SPI failed")
+        .expect("got NULL");
+        // 3 of 5 distinct pairs are shared
+        assert!((similarity - 0.6).abs() < 0.15, "similarity {similarity}");
+
+        let empty = Spi::get_one::<bool>(
+            "SELECT minhash_agg(i::text, 64) = '{}' FROM generate_series(1, 0) i;",
+        );
+        assert_eq!(empty, Ok(Some(true)));
+    }
+
+    #[pg_test(error = "minhash signature size changed from 64 to 32")]
+    fn pg_test_minhash_agg_size_changed() {
+        Spi::run(
+            "SELECT minhash_agg(i::text, CASE WHEN i = 1 THEN 64 ELSE 32 END) FROM generate_series(1, 2) i;",
+        )
+        .expect("SPI failed");
+    }
+}
@@ -0,0 +1,186 @@
+//! Hash operator classes using the extension's fingerprints, so that e.g. the partition of a
+//! `PARTITION BY HASH` table can be computed outside of the database, see
+//! `pg_row_hashes_fingerprint::partition`.
+//!
+//! They are not the default operator classes of their types and have to be named explicitly.
+
+use pg_row_hashes_fingerprint::partition::hash_extended;
+use pg_row_hashes_fingerprint::{Algorithm as _, Farmhash, Seahash};
+use pgrx::{prelude::*, Uuid};
+
+extension_sql!(
+    r#"
+CREATE OPERATOR CLASS farmhash_text_ops FOR TYPE text USING hash AS
+    OPERATOR 1 = (text, text),
+    FUNCTION 1 farmhash_text_hash(text),
+    FUNCTION 2 farmhash_text_hash_extended(text, bigint);
+
+CREATE OPERATOR CLASS seahash_text_ops FOR TYPE text USING hash AS
+    OPERATOR 1 = (text, text),
+    FUNCTION 1 seahash_text_hash(text),
+    FUNCTION 2 seahash_text_hash_extended(text, bigint);
+
+CREATE OPERATOR CLASS farmhash_uuid_ops FOR TYPE uuid USING hash AS
+    OPERATOR 1 = (uuid, uuid),
+    FUNCTION 1 farmhash_uuid_hash(uuid),
+    FUNCTION 2 farmhash_uuid_hash_extended(uuid, bigint);
+"#,
+    name = "hash_operator_classes",
+    requires = [
+        farmhash_text_hash,
+        farmhash_text_hash_extended,
+        seahash_text_hash,
+        seahash_text_hash_extended,
+        farmhash_uuid_hash,
+        farmhash_uuid_hash_extended,
+    ],
+);
+
+/// Hash of `farmhash_text_ops`: the low 64 bits of `id_farmhash(value)`.
+fn farmhash_text(value: &str) -> u64 {
+    Farmhash::fingerprint(value.as_bytes()) as u64
+}
+
+/// Hash of `seahash_text_ops`: `id_seahash(value)`.
+fn seahash_text(value: &str) -> u64 {
+    Seahash::fingerprint(value.as_bytes())
+}
+
+/// Hash of `farmhash_uuid_ops`: the low 64 bits of the fingerprint.
+fn farmhash_uuid(value: Uuid) -> u64 {
+    u128::from_le_bytes(*value.as_bytes()) as u64
+}
+
+/// Standard hash support function: the low 32 bits.
+fn hash32(hash: u64) -> i32 {
+    hash as u32 as i32
+}
+
+/// Extended hash support function.
+fn hash64(hash: u64, seed: i64) -> i64 {
+    hash_extended(hash, seed as u64) as i64
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Hash support function of farmhash_text_ops
+pub fn farmhash_text_hash(value: &str) -> i32 {
+    hash32(farmhash_text(value))
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Extended hash support function of farmhash_text_ops
+pub fn farmhash_text_hash_extended(value: &str, seed: i64) -> i64 {
+    hash64(farmhash_text(value), seed)
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Hash support function of seahash_text_ops
+pub fn seahash_text_hash(value: &str) -> i32 {
+    hash32(seahash_text(value))
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Extended hash support function of seahash_text_ops
+pub fn seahash_text_hash_extended(value: &str, seed: i64) -> i64 {
+    hash64(seahash_text(value), seed)
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Hash support function of farmhash_uuid_ops
+pub fn farmhash_uuid_hash(value: Uuid) -> i32 {
+    hash32(farmhash_uuid(value))
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Extended hash support function of farmhash_uuid_ops
+pub fn farmhash_uuid_hash_extended(value: Uuid, seed: i64) -> i64 {
+    hash64(farmhash_uuid(value), seed)
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pg_schema]
+mod tests {
+    use pg_row_hashes_fingerprint::partition::hash_partition;
+    use pgrx::prelude::*;
+
+    use super::{farmhash_text, seahash_text};
+
+    #[pg_test]
+    fn pg_test_hash_partitions() {
+        for (opclass, hash) in [
+            ("farmhash_text_ops", farmhash_text as fn(&str) -> u64),
+            ("seahash_text_ops", seahash_text),
+        ] {
+            Spi::run(&format!(
+                "CREATE TABLE partition_test (t text) PARTITION BY HASH (t {opclass});
+                 CREATE TABLE partition_test_0 PARTITION OF partition_test FOR VALUES WITH (MODULUS 3, REMAINDER 0);
+                 CREATE TABLE partition_test_1 PARTITION OF partition_test FOR VALUES WITH (MODULUS 3, REMAINDER 1);
+                 CREATE TABLE partition_test_2 PARTITION OF partition_test FOR VALUES WITH (MODULUS 3, REMAINDER 2);
+                 INSERT INTO partition_test SELECT md5(i::text) FROM generate_series(1, 100) i;"
+            ))
+            .expect("SPI failed");
+
+            let rows = Spi::connect(|client| {
+                client
+                    .select(
+                        "SELECT t, right(tableoid::regclass::text, 1)::int FROM partition_test;",
+                        None,
+                        &[],
+                    )?
+                    .map(|row| {
+                        Ok((
+                            row.get::<String>(1)?.expect("got None"),
+                            row.get::<i32>(2)?.expect("got None"),
+                        ))
+                    })
+                    .collect::<Result<Vec<_>, pgrx::spi::Error>>()
+            })
+            .expect("SPI failed");
+
+            assert_eq!(rows.len(), 100);
+            for (t, remainder) in rows {
+                assert_eq!(
+                    hash_partition([Some(hash(&t))], 3),
+                    remainder as u64,
+                    "using {t} and {opclass}"
+                );
+            }
+            Spi::run("DROP TABLE partition_test;").expect("SPI failed");
+        }
+    }
+
+    #[pg_test]
+    fn pg_test_hash_opclasses() {
+        for (query, golden) in [
+            // the standard and extended functions agree for seed 0
+            (
+                "SELECT bool_and(farmhash_text_hash(t)::bigint & x'ffffffff'::bigint = farmhash_text_hash_extended(t, 0) & x'ffffffff'::bigint
+                    AND seahash_text_hash(t)::bigint & x'ffffffff'::bigint = seahash_text_hash_extended(t, 0) & x'ffffffff'::bigint
+                    AND farmhash_uuid_hash(id_farmhash(t)) = farmhash_text_hash(t))
+                 FROM (SELECT md5(i::text) FROM generate_series(1, 100) i) s(t);",
+                true,
+            ),
+            // a uuid fingerprint is in the same partition as the text it's the fingerprint of
+            (
+                "SELECT farmhash_uuid_hash_extended(id_farmhash('a'), 42) = farmhash_text_hash_extended('a', 42);",
+                true,
+            ),
+        ] {
+            let result = Spi::get_one::<bool>(query);
+            assert_eq!(result, Ok(Some(golden)), "using {query}");
+        }
+
+        Spi::run(
+            "CREATE TABLE opclass_test (t text, f uuid);
+             CREATE INDEX ON opclass_test USING hash (t seahash_text_ops);
+             CREATE INDEX ON opclass_test USING hash (f farmhash_uuid_ops);
+             INSERT INTO opclass_test SELECT md5(i::text), id_farmhash(i::text) FROM generate_series(1, 1000) i;
+             SET LOCAL enable_seqscan = off;",
+        )
+        .expect("SPI failed");
+        let result = Spi::get_one::<i64>(
+            "SELECT count(*) FROM opclass_test WHERE t = md5('1') OR f = id_farmhash('2');",
+        );
+        assert_eq!(result, Ok(Some(2)));
+    }
+}
@@ -0,0 +1,341 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::ffi::CStr;
+use std::num::NonZeroUsize;
+
+use bytes::Bytes;
+use pgrx::{pg_sys, prelude::*, AnyElement, PgTupleDesc, Uuid};
+use regex::Regex;
+
+use pg_row_hashes_fingerprint::{checksum_bytes, NullPolicy};
+
+use crate::{farmhash_fingerprint, seahash_fingerprint};
+
+/// Patterns compiled by [`ColumnFilter::with_patterns`] are kept for reuse up to this many.
+const PATTERN_CACHE_SIZE: usize = 64;
+
+thread_local! {
+    /// Compiled column patterns by their source, so that a query compiles each pattern once.
+    static PATTERNS: RefCell<HashMap<String, Regex>> = RefCell::default();
+}
+
+/// Compile `pattern`, or return the copy compiled by an earlier call.
+fn compile_pattern(pattern: &str) -> Result<Regex, regex::Error> {
+    PATTERNS.with_borrow_mut(|cache| {
+        if let Some(regex) = cache.get(pattern) {
+            return Ok(regex.clone());
+        }
+        let regex = Regex::new(pattern)?;
+        if cache.len() >= PATTERN_CACHE_SIZE {
+            cache.clear();
+        }
+        cache.insert(pattern.to_string(), regex.clone());
+        Ok(regex)
+    })
+}
+
+/// Selects the columns of a row that take part in its fingerprint.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ColumnFilter {
+    include: Option<HashSet<String>>,
+    exclude: HashSet<String>,
+    include_pattern: Option<Regex>,
+    exclude_pattern: Option<Regex>,
+}
+
+impl ColumnFilter {
+    pub(crate) fn new(
+        include: Option<impl IntoIterator<Item = String>>,
+        exclude: impl IntoIterator<Item = String>,
+    ) -> Self {
+        ColumnFilter {
+            include: include.map(|i| i.into_iter().collect()),
+            exclude: exclude.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Additionally filter columns by regular expressions on their names.
+    pub(crate) fn with_patterns(
+        mut self,
+        include_pattern: Option<&str>,
+        exclude_pattern: Option<&str>,
+    ) -> Result<Self, regex::Error> {
+        self.include_pattern = include_pattern.map(compile_pattern).transpose()?;
+        self.exclude_pattern = exclude_pattern.map(compile_pattern).transpose()?;
+        Ok(self)
+    }
+
+    /// Additionally exclude `column`.
+    pub(crate) fn exclude(&mut self, column: impl Into<String>) {
+        self.exclude.insert(column.into());
+    }
+
+    pub(crate) fn matches(&self, column: &str) -> bool {
+        if self.exclude.contains(column)
+            || self
+                .exclude_pattern
+                .as_ref()
+                .is_some_and(|p| p.is_match(column))
+        {
+            return false;
+        }
+        self.include.as_ref().is_none_or(|i| i.contains(column))
+            && self
+                .include_pattern
+                .as_ref()
+                .is_none_or(|p| p.is_match(column))
+    }
+}
+
+#[pg_extern(stable, parallel_safe, create_or_replace)]
+/// Hash the columns of a row into a Uuid using farmhash's fingerprint128, like `checksum_farmhash` with the column names as keys
+pub fn checksum_farmhash_record(
+    r: Option<AnyElement>,
+    include: default!(Option<Vec<String>>, "NULL"),
+    exclude: default!(Option<Vec<String>>, "NULL"),
+    include_pattern: default!(Option<String>, "NULL"),
+    exclude_pattern: default!(Option<String>, "NULL"),
+) -> Option<Uuid> {
+    let r = r?;
+    let filter = column_filter(include, exclude, include_pattern, exclude_pattern);
+    Some(farmhash_fingerprint(record_checksum_bytes(
+        &r,
+        &filter,
+        NullPolicy::Keep,
+    )))
+}
+
+#[pg_extern(stable, parallel_safe, create_or_replace)]
+/// Hash the columns of a row into a Uuid using farmhash's fingerprint128, like `checksum_farmhash_extendable` with the column names as keys
+pub fn checksum_farmhash_extendable_record(
+    r: Option<AnyElement>,
+    include: default!(Option<Vec<String>>, "NULL"),
+    exclude: default!(Option<Vec<String>>, "NULL"),
+    include_pattern: default!(Option<String>, "NULL"),
+    exclude_pattern: default!(Option<String>, "NULL"),
+) -> Option<Uuid> {
+    let r = r?;
+    let filter = column_filter(include, exclude, include_pattern, exclude_pattern);
+    Some(farmhash_fingerprint(record_checksum_bytes(
+        &r,
+        &filter,
+        NullPolicy::Skip,
+    )))
+}
+
+#[pg_extern(stable, parallel_safe, create_or_replace)]
+/// Hash the columns of a row into a bigint using seahash, like `checksum_seahash` with the column names as keys
+pub fn checksum_seahash_record(
+    r: Option<AnyElement>,
+    include: default!(Option<Vec<String>>, "NULL"),
+    exclude: default!(Option<Vec<String>>, "NULL"),
+    include_pattern: default!(Option<String>, "NULL"),
+    exclude_pattern: default!(Option<String>, "NULL"),
+) -> Option<i64> {
+    let r = r?;
+    let filter = column_filter(include, exclude, include_pattern, exclude_pattern);
+    Some(seahash_fingerprint(record_checksum_bytes(
+        &r,
+        &filter,
+        NullPolicy::Keep,
+    )))
+}
+
+#[pg_extern(stable, parallel_safe, create_or_replace)]
+/// Hash the columns of a row into a bigint using seahash, like `checksum_seahash_extendable` with the column names as keys
+pub fn checksum_seahash_extendable_record(
+    r: Option<AnyElement>,
+    include: default!(Option<Vec<String>>, "NULL"),
+    exclude: default!(Option<Vec<String>>, "NULL"),
+    include_pattern: default!(Option<String>, "NULL"),
+    exclude_pattern: default!(Option<String>, "NULL"),
+) -> Option<i64> {
+    let r = r?;
+    let filter = column_filter(include, exclude, include_pattern, exclude_pattern);
+    Some(seahash_fingerprint(record_checksum_bytes(
+        &r,
+        &filter,
+        NullPolicy::Skip,
+    )))
+}
+
+fn column_filter(
+    include: Option<Vec<String>>,
+    exclude: Option<Vec<String>>,
+    include_pattern: Option<String>,
+    exclude_pattern: Option<String>,
+) -> ColumnFilter {
+    ColumnFilter::new(include, exclude.unwrap_or_default())
+        .with_patterns(include_pattern.as_deref(), exclude_pattern.as_deref())
+        .unwrap_or_else(|e| error!("invalid column pattern: {e}"))
+}
+
+#[inline]
+fn record_checksum_bytes(r: &AnyElement, filter: &ColumnFilter, nulls: NullPolicy) -> Bytes {
+    if !unsafe { pg_sys::type_is_rowtype(r.oid()) } {
+        error!("expected a row type argument");
+    }
+    let pairs = unsafe { composite_pairs(r.datum(), filter) };
+    checksum_bytes(pairs, nulls)
+}
+
+/// Like [`tuple_pairs`] for a composite datum, e.g. a `record` function argument.
+///
+/// # Safety
+///
+/// `datum` has to be a non-NULL composite datum.
+pub(crate) unsafe fn composite_pairs(
+    datum: pg_sys::Datum,
+    filter: &ColumnFilter,
+) -> Vec<Option<String>> {
+    unsafe {
+        let header = pg_sys::pg_detoast_datum(datum.cast_mut_ptr()) as pg_sys::HeapTupleHeader;
+        let tupdesc = PgTupleDesc::from_pg_is_copy(pg_sys::lookup_rowtype_tupdesc_copy(
+            pgrx::heap_tuple_header_get_type_id(header),
+            pgrx::heap_tuple_header_get_typmod(header),
+        ));
+        let tuple = pg_sys::HeapTupleData {
+            t_len: pgrx::heap_tuple_header_get_datum_length(header) as u32,
+            t_data: header,
+            ..Default::default()
+        };
+        tuple_pairs(&tuple, &tupdesc, filter)
+    }
+}
+
+/// Render the columns of `tuple` as a flat list of key, value pairs, the way they would be
+/// passed to the `checksum_*` functions: column name followed by the text output of its value.
+///
+/// # Safety
+///
+/// `tuple` has to be a valid heap tuple described by `tupdesc`.
+pub(crate) unsafe fn tuple_pairs(
+    tuple: *const pg_sys::HeapTupleData,
+    tupdesc: &PgTupleDesc,
+    filter: &ColumnFilter,
+) -> Vec<Option<String>> {
+    let mut pairs = Vec::with_capacity(tupdesc.len() * 2);
+    for (i, att) in tupdesc.iter().enumerate() {
+        if att.is_dropped() || !filter.matches(att.name()) {
+            continue;
+        }
+        let attno = NonZeroUsize::new(i + 1).expect("attribute numbers start at 1");
+        let value = unsafe { pgrx::heap_getattr_raw(tuple, attno, tupdesc.as_ptr()) }
+            .map(|datum| unsafe { output_text(datum, att.atttypid) });
+
+        pairs.push(Some(att.name().to_string()));
+        pairs.push(value);
+    }
+    pairs
+}
+
+/// Text representation of `datum` using the type's output function, i.e. what `datum::text` returns.
+///
+/// It depends on session settings like `TimeZone`, `DateStyle`, `IntervalStyle`,
+/// `extra_float_digits` and `bytea_output`, see the README.
+pub(crate) unsafe fn output_text(datum: pg_sys::Datum, typoid: pg_sys::Oid) -> String {
+    let mut output_fn = pg_sys::InvalidOid;
+    let mut is_varlena = false;
+    unsafe {
+        pg_sys::getTypeOutputInfo(typoid, &mut output_fn, &mut is_varlena);
+        let cstr = pg_sys::OidOutputFunctionCall(output_fn, datum);
+        let text = CStr::from_ptr(cstr).to_string_lossy().into_owned();
+        pg_sys::pfree(cstr.cast());
+        text
+    }
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pg_schema]
+mod tests {
+    use pgrx::prelude::*;
+    use pgrx::Uuid;
+
+    use super::ColumnFilter;
+
+    #[test]
+    fn test_column_filter() {
+        let filter = ColumnFilter::new(None::<Vec<String>>, vec!["updated_at".to_string()]);
+        assert!(filter.matches("id"));
+        assert!(!filter.matches("updated_at"));
+
+        let mut filter = ColumnFilter::new(
+            Some(vec!["a".to_string(), "b".to_string()]),
+            Vec::<String>::new(),
+        );
+        assert!(filter.matches("a"));
+        assert!(!filter.matches("c"));
+
+        filter.exclude("a");
+        assert!(!filter.matches("a"));
+        assert!(filter.matches("b"));
+    }
+
+    #[test]
+    fn test_column_filter_patterns() {
+        let filter = ColumnFilter::new(None::<Vec<String>>, vec!["id".to_string()])
+            .with_patterns(None, Some("_at$"))
+            .expect("valid pattern");
+        assert!(filter.matches("name"));
+        assert!(!filter.matches("id"));
+        assert!(!filter.matches("created_at"));
+
+        let filter = ColumnFilter::new(None::<Vec<String>>, Vec::<String>::new())
+            .with_patterns(Some("^addr_"), Some("_id$"))
+            .expect("valid pattern");
+        assert!(filter.matches("addr_street"));
+        assert!(!filter.matches("addr_id"));
+        assert!(!filter.matches("name"));
+
+        assert!(ColumnFilter::default()
+            .with_patterns(Some("("), None)
+            .is_err());
+    }
+
+    #[pg_test]
+    fn pg_test_checksum_record() {
+        Spi::run(
+            "CREATE TABLE record_test (id int, name text, note text, updated_at timestamptz);
+             INSERT INTO record_test VALUES (1, 'a', NULL, now());",
+        )
+        .expect("SPI failed");
+
+        for (query, golden) in [
+            (
+                "SELECT checksum_farmhash_record(t, exclude => '{updated_at}') FROM record_test t;",
+                "SELECT checksum_farmhash('id', '1', 'name', 'a', 'note', NULL);",
+            ),
+            (
+                "SELECT checksum_farmhash_extendable_record(t, include => '{id,note}') FROM record_test t;",
+                "SELECT checksum_farmhash_extendable('id', '1');",
+            ),
+            (
+                "SELECT checksum_farmhash_record(t, exclude => '{id}', exclude_pattern => '_at$') FROM record_test t;",
+                "SELECT checksum_farmhash('name', 'a', 'note', NULL);",
+            ),
+            (
+                "SELECT checksum_farmhash_record(ROW(1, 'a', NULL, NULL)::record_test);",
+                "SELECT checksum_farmhash('id', '1', 'name', 'a', 'note', NULL, 'updated_at', NULL);",
+            ),
+        ] {
+            let result = Spi::get_one::<Uuid>(query)
+                .expect("didn't get SPI result")
+                .expect("got None");
+            let golden = Spi::get_one::<Uuid>(golden)
+                .expect("didn't get SPI result")
+                .expect("got None");
+            assert_eq!(result, golden, "using {}", query);
+        }
+
+        let result = Spi::get_one::<i64>(
+            "SELECT checksum_seahash_record(t, include_pattern => '^(id|name)$') FROM record_test t;",
+        )
+        .expect("didn't get SPI result")
+        .expect("got None");
+        let golden = Spi::get_one::<i64>("SELECT checksum_seahash('id', '1', 'name', 'a');")
+            .expect("didn't get SPI result")
+            .expect("got None");
+        assert_eq!(result, golden);
+    }
+}
@@ -0,0 +1,304 @@
+//! Deterministic samples by fingerprint, see `pg_row_hashes_fingerprint::sample`: the same ids are
+//! sampled in every table and every run, unlike with `TABLESAMPLE bernoulli` or `random()`.
+//!
+//! The `TABLESAMPLE` method's handler takes and returns `internal`, so it is defined in SQL below
+//! instead of with `#[pg_extern]`.
+
+use std::ffi::CStr;
+use std::ptr;
+
+use pg_row_hashes_fingerprint::sample::sampled;
+use pg_row_hashes_fingerprint::{id_fingerprint, Algorithm as _, Seahash};
+use pgrx::{
+    pg_sys, prelude::*, FromDatum, PgBox, PgList, PgMemoryContexts, PgTupleDesc, VariadicArray,
+};
+
+extension_sql!(
+    r#"
+CREATE OR REPLACE FUNCTION fingerprint_sample(internal) RETURNS tsm_handler
+    STRICT LANGUAGE c AS 'MODULE_PATHNAME', 'fingerprint_sample_tsm_handler';
+"#,
+    name = "fingerprint_sample_tsm_handler",
+);
+
+/// Sample percentage when planning with a non-constant percentage, like `bernoulli`.
+const DEFAULT_PERCENT: f64 = 10.0;
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Whether the ids are in the sample of about rate (0 to 1) of all ids, by their id_seahash
+pub fn fingerprint_sample(rate: f64, ids: VariadicArray<&str>) -> bool {
+    if !(0.0..=1.0).contains(&rate) {
+        error!("sample rate must be between 0 and 1, got {rate}");
+    }
+    sampled(id_fingerprint::<Seahash, _>(ids.iter_deny_null()), rate)
+}
+
+pg_finfo_v1!(pg_finfo_fingerprint_sample_tsm_handler);
+
+#[pg_guard]
+#[unsafe(no_mangle)]
+/// `TABLESAMPLE fingerprint_sample(column, percent)`: the rows whose column is in
+/// `fingerprint_sample(percent / 100, column::text)`.
+///
+/// The column of every row has to be read to decide, so every block is read and the method is
+/// no cheaper than filtering with `WHERE fingerprint_sample(...)`, only more convenient.
+pub unsafe extern "C-unwind" fn fingerprint_sample_tsm_handler(
+    _fcinfo: pg_sys::FunctionCallInfo,
+) -> pg_sys::Datum {
+    unsafe {
+        let mut tsm = PgBox::<pg_sys::TsmRoutine>::alloc_node(pg_sys::NodeTag::T_TsmRoutine);
+        let mut parameter_types = ptr::null_mut();
+        parameter_types = pg_sys::lappend_oid(parameter_types, pg_sys::TEXTOID);
+        parameter_types = pg_sys::lappend_oid(parameter_types, pg_sys::FLOAT8OID);
+        tsm.parameterTypes = parameter_types;
+        tsm.repeatable_across_queries = true;
+        tsm.repeatable_across_scans = true;
+        tsm.SampleScanGetSampleSize = Some(sample_scan_get_sample_size);
+        tsm.InitSampleScan = Some(init_sample_scan);
+        tsm.BeginSampleScan = Some(begin_sample_scan);
+        // every block is scanned
+        tsm.NextSampleBlock = None;
+        tsm.NextSampleTuple = Some(next_sample_tuple);
+        tsm.EndSampleScan = Some(end_sample_scan);
+        pg_sys::Datum::from(tsm.into_pg())
+    }
+}
+
+/// State of a `TABLESAMPLE fingerprint_sample` scan.
+struct SampleState {
+    /// Slot to fetch the rows into.
+    slot: *mut pg_sys::TupleTableSlot,
+    /// Number of the sampled column.
+    attnum: pg_sys::AttrNumber,
+    /// Output function of the sampled column's type, looked up once per scan.
+    output_fn: pg_sys::FmgrInfo,
+    rate: f64,
+    /// Offset of the last row of the current block returned, 0 before the first one.
+    offset: pg_sys::OffsetNumber,
+}
+
+impl SampleState {
+    /// Whether the row at `offset` of `block` is in the sample.
+    ///
+    /// The scan checks the visibility of the rows returned itself, so the row is fetched without
+    /// checking it here as well.
+    unsafe fn contains(
+        &mut self,
+        rel: pg_sys::Relation,
+        block: pg_sys::BlockNumber,
+        offset: pg_sys::OffsetNumber,
+    ) -> bool {
+        unsafe {
+            let mut tid = pg_sys::ItemPointerData::default();
+            tid.ip_blkid.bi_hi = (block >> 16) as u16;
+            tid.ip_blkid.bi_lo = (block & 0xffff) as u16;
+            tid.ip_posid = offset;
+
+            let fetch_row_version = (*(*rel).rd_tableam)
+                .tuple_fetch_row_version
+                .expect("table access methods fetch rows");
+            if !fetch_row_version(
+                rel,
+                &mut tid,
+                ptr::addr_of_mut!(pg_sys::SnapshotAnyData),
+                self.slot,
+            ) {
+                return false;
+            }
+
+            pg_sys::slot_getsomeattrs_int(self.slot, self.attnum.into());
+            let i = self.attnum as usize - 1;
+            if *(*self.slot).tts_isnull.add(i) {
+                return false;
+            }
+            let cstr =
+                pg_sys::OutputFunctionCall(&mut self.output_fn, *(*self.slot).tts_values.add(i));
+            let fingerprint = Seahash::fingerprint(CStr::from_ptr(cstr).to_bytes());
+            pg_sys::pfree(cstr.cast());
+            sampled(fingerprint, self.rate)
+        }
+    }
+}
+
+#[pg_guard]
+unsafe extern "C-unwind" fn sample_scan_get_sample_size(
+    root: *mut pg_sys::PlannerInfo,
+    baserel: *mut pg_sys::RelOptInfo,
+    paramexprs: *mut pg_sys::List,
+    pages: *mut pg_sys::BlockNumber,
+    tuples: *mut f64,
+) {
+    unsafe {
+        let percent = PgList::<pg_sys::Node>::from_pg(paramexprs)
+            .get_ptr(1)
+            .map(|expr| pg_sys::estimate_expression_value(root, expr))
+            .filter(|&expr| pgrx::is_a(expr, pg_sys::NodeTag::T_Const))
+            .map(|expr| expr.cast::<pg_sys::Const>())
+            .and_then(|c| f64::from_datum((*c).constvalue, (*c).constisnull))
+            .filter(|percent| (0.0..=100.0).contains(percent))
+            .unwrap_or(DEFAULT_PERCENT);
+
+        *pages = (*baserel).pages;
+        *tuples = ((*baserel).tuples * percent / 100.0).round().max(1.0);
+    }
+}
+
+#[pg_guard]
+unsafe extern "C-unwind" fn init_sample_scan(node: *mut pg_sys::SampleScanState, _eflags: i32) {
+    unsafe {
+        let slot = pg_sys::table_slot_create((*node).ss.ss_currentRelation, ptr::null_mut());
+        let state = SampleState {
+            slot,
+            attnum: 0,
+            output_fn: pg_sys::FmgrInfo::default(),
+            rate: 0.0,
+            offset: 0,
+        };
+        (*node).tsm_state = PgMemoryContexts::CurrentMemoryContext
+            .leak_and_drop_on_delete(state)
+            .cast();
+    }
+}
+
+#[pg_guard]
+unsafe extern "C-unwind" fn begin_sample_scan(
+    node: *mut pg_sys::SampleScanState,
+    params: *mut pg_sys::Datum,
+    _nparams: i32,
+    _seed: u32,
+) {
+    unsafe {
+        // NULL arguments are rejected before
+        let column = String::from_datum(*params, false).expect("column is not NULL");
+        let percent = f64::from_datum(*params.add(1), false).expect("percent is not NULL");
+        if !(0.0..=100.0).contains(&percent) {
+            error!("sample percentage must be between 0 and 100, got {percent}");
+        }
+
+        let tupdesc = PgTupleDesc::from_pg_unchecked((*(*node).ss.ss_currentRelation).rd_att);
+        let Some((i, att)) = tupdesc
+            .iter()
+            .enumerate()
+            .find(|(_, att)| !att.is_dropped() && att.name() == column)
+        else {
+            error!("column \"{column}\" does not exist");
+        };
+
+        let mut output_fn = pg_sys::InvalidOid;
+        let mut is_varlena = false;
+        pg_sys::getTypeOutputInfo(att.atttypid, &mut output_fn, &mut is_varlena);
+
+        let state = &mut *(*node).tsm_state.cast::<SampleState>();
+        state.attnum = (i + 1) as pg_sys::AttrNumber;
+        pg_sys::fmgr_info(output_fn, &mut state.output_fn);
+        state.rate = percent / 100.0;
+        state.offset = 0;
+    }
+}
+
+#[pg_guard]
+unsafe extern "C-unwind" fn next_sample_tuple(
+    node: *mut pg_sys::SampleScanState,
+    block: pg_sys::BlockNumber,
+    maxoffset: pg_sys::OffsetNumber,
+) -> pg_sys::OffsetNumber {
+    unsafe {
+        let state = &mut *(*node).tsm_state.cast::<SampleState>();
+        let rel = (*node).ss.ss_currentRelation;
+
+        // returning InvalidOffsetNumber (0) ends the block, the next one starts from 1 again
+        state.offset = (state.offset + 1..=maxoffset)
+            .find(|&offset| state.contains(rel, block, offset))
+            .unwrap_or(0);
+        state.offset
+    }
+}
+
+#[pg_guard]
+unsafe extern "C-unwind" fn end_sample_scan(node: *mut pg_sys::SampleScanState) {
+    unsafe {
+        let state = &*(*node).tsm_state.cast::<SampleState>();
+        pg_sys::ExecDropSingleTupleTableSlot(state.slot);
+    }
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pg_schema]
+mod tests {
+    use pgrx::prelude::*;
+
+    #[pg_test]
+    fn pg_test_fingerprint_sample() {
+        for (query, golden) in [
+            ("SELECT fingerprint_sample(0.0, 'customer', '42');", false),
+            ("SELECT fingerprint_sample(1.0, 'customer', '42');", true),
+            (
+                "SELECT count(*) FILTER (WHERE fingerprint_sample(0.1, i::text)) BETWEEN 800 AND 1200
+                 FROM generate_series(1, 10000) i;",
+                true,
+            ),
+            // the sample of a smaller rate is part of the sample of a larger one
+            (
+                "SELECT bool_and(fingerprint_sample(0.2, i::text))
+                 FROM generate_series(1, 10000) i WHERE fingerprint_sample(0.1, i::text);",
+                true,
+            ),
+        ] {
+            let result = Spi::get_one::<bool>(query);
+            assert_eq!(result, Ok(Some(golden)), "using {query}");
+        }
+    }
+
+    #[pg_test]
+    fn pg_test_tablesample() {
+        Spi::run(
+            "CREATE TABLE sample_test (a text, customer_id int);
+             ALTER TABLE sample_test DROP COLUMN a;
+             INSERT INTO sample_test SELECT i FROM generate_series(1, 10000) i;
+             DELETE FROM sample_test WHERE customer_id % 3 = 0;
+             CREATE TABLE sample_test_orders AS
+                SELECT customer_id, i AS order_id FROM sample_test, generate_series(1, 2) i;",
+        )
+        .expect("SPI failed");
+
+        for (query, golden) in [
+            // the same rows as filtering by fingerprint_sample
+            (
+                "SELECT count(*) FROM sample_test TABLESAMPLE fingerprint_sample('customer_id', 10);",
+                Spi::get_one::<i64>(
+                    "SELECT count(*) FROM sample_test WHERE fingerprint_sample(0.1, customer_id::text);",
+                )
+                .expect("SPI failed")
+                .expect("got None"),
+            ),
+            (
+                "SELECT count(*) FROM (
+                    SELECT customer_id FROM sample_test TABLESAMPLE fingerprint_sample('customer_id', 10)
+                    EXCEPT
+                    SELECT customer_id FROM sample_test WHERE fingerprint_sample(0.1, customer_id::text)
+                 ) s;",
+                0,
+            ),
+            // samples of different tables agree
+            (
+                "SELECT count(*) FROM (
+                    SELECT customer_id FROM sample_test_orders TABLESAMPLE fingerprint_sample('customer_id', 10)
+                    EXCEPT
+                    SELECT customer_id FROM sample_test TABLESAMPLE fingerprint_sample('customer_id', 10)
+                 ) s;",
+                0,
+            ),
+            (
+                "SELECT count(*) FROM sample_test TABLESAMPLE fingerprint_sample('customer_id', 0);",
+                0,
+            ),
+            (
+                "SELECT count(*) FROM sample_test TABLESAMPLE fingerprint_sample('customer_id', 100);",
+                6667,
+            ),
+        ] {
+            let result = Spi::get_one::<i64>(query);
+            assert_eq!(result, Ok(Some(golden)), "using {query}");
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use pg_row_hashes_fingerprint::{id_fingerprint, shard, Seahash};
+use pgrx::{prelude::*, Array, VariadicArray};
+
+/// Key of the ids to shard by, their `id_seahash`.
+fn shard_key(ids: &VariadicArray<&str>) -> u64 {
+    id_fingerprint::<Seahash, _>(ids.iter_deny_null())
+}
+
+fn shard_count(n_shards: i32) -> u32 {
+    if n_shards <= 0 {
+        error!("number of shards must be positive, got {n_shards}");
+    }
+    n_shards as u32
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Shard (0 to n_shards - 1) of the ids: id_seahash of the ids modulo n_shards
+pub fn shard_for(n_shards: i32, ids: VariadicArray<&str>) -> i32 {
+    shard::modulo(shard_key(&ids), shard_count(n_shards)) as i32
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Shard (0 to n_shards - 1) of the ids by jump consistent hashing of their id_seahash
+pub fn shard_for_jump(n_shards: i32, ids: VariadicArray<&str>) -> i32 {
+    shard::jump(shard_key(&ids), shard_count(n_shards)) as i32
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Shard of the ids out of the named shards by rendezvous hashing of their id_seahash
+pub fn shard_for_rendezvous(shards: Array<&str>, ids: VariadicArray<&str>) -> String {
+    shard::rendezvous(shard_key(&ids), shards.iter_deny_null())
+        .unwrap_or_else(|| error!("there must be at least one shard"))
+        .to_string()
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pg_schema]
+mod tests {
+    use pg_row_hashes_fingerprint::{id_fingerprint, shard, Seahash};
+    use pgrx::prelude::*;
+
+    #[pg_test]
+    fn pg_test_shard_for() {
+        let key = id_fingerprint::<Seahash, _>(["customer", "42"]);
+        for (query, golden) in [
+            (
+                "SELECT shard_for(7, 'customer', '42')::text;",
+                shard::modulo(key, 7).to_string(),
+            ),
+            (
+                "SELECT shard_for_jump(7, 'customer', '42')::text;",
+                shard::jump(key, 7).to_string(),
+            ),
+            (
+                "SELECT shard_for_rendezvous('{db1,db2,db3}', 'customer', '42');",
+                shard::rendezvous(key, ["db1", "db2", "db3"])
+                    .expect("there are shards")
+                    .to_string(),
+            ),
+        ] {
+            let result = Spi::get_one::<String>(query);
+            assert_eq!(result, Ok(Some(golden)), "using {query}");
+        }
+
+        // growing from 4 to 5 shards only moves rows to the new shard
+        let moved = Spi::get_one::<bool>(
+            "SELECT bool_and(shard_for_jump(5, i::text) IN (shard_for_jump(4, i::text), 4))
+                AND count(*) FILTER (WHERE shard_for_jump(5, i::text) = 4) BETWEEN 100 AND 300
+             FROM generate_series(1, 1000) i;",
+        );
+        assert_eq!(moved, Ok(Some(true)));
+    }
+}
@@ -0,0 +1,134 @@
+use pg_row_hashes_fingerprint::{Algorithm, Farmhash, Seahash};
+use pgrx::{prelude::*, Uuid};
+
+/// Character shingles of `text`, the whole text if it is shorter than `size`.
+fn shingles(text: &str, size: i32) -> Vec<&str> {
+    if size <= 0 {
+        error!("shingle size must be positive, got {size}");
+    }
+    let size = size as usize;
+    let boundaries: Vec<usize> = text
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain([text.len()])
+        .collect();
+    if boundaries.len() <= size {
+        return [text].into_iter().filter(|t| !t.is_empty()).collect();
+    }
+    boundaries
+        .windows(size + 1)
+        .map(|w| &text[w[0]..w[size]])
+        .collect()
+}
+
+/// SimHash of `BITS` bits: every bit is set if it is set in the majority of the shingles' hashes.
+fn simhash<const BITS: usize>(shingles: &[&str], hash: impl Fn(&[u8]) -> u128) -> u128 {
+    let mut weights = [0i64; BITS];
+    for shingle in shingles {
+        let h = hash(shingle.as_bytes());
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            *weight += if h >> bit & 1 == 1 { 1 } else { -1 };
+        }
+    }
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, &weight)| weight > 0)
+        .fold(0, |h, (bit, _)| h | 1 << bit)
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// SimHash of the character shingles of a text into a bigint using seahash
+pub fn simhash_seahash(text: &str, shingle_size: default!(i32, 3)) -> i64 {
+    simhash::<64>(&shingles(text, shingle_size), |s| {
+        Seahash::fingerprint(s).into()
+    }) as u64 as i64
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// SimHash of the character shingles of a text into a Uuid using farmhash's fingerprint128
+pub fn simhash_farmhash(text: &str, shingle_size: default!(i32, 3)) -> Uuid {
+    let h = simhash::<128>(&shingles(text, shingle_size), Farmhash::fingerprint);
+    Uuid::from_bytes(h.to_le_bytes())
+}
+
+#[pg_extern(strict, immutable, parallel_safe, create_or_replace)]
+/// Number of differing bits of two bigint fingerprints
+pub fn hamming_distance(a: i64, b: i64) -> i32 {
+    (a ^ b).count_ones() as i32
+}
+
+#[pg_extern(
+    strict,
+    immutable,
+    parallel_safe,
+    create_or_replace,
+    name = "hamming_distance"
+)]
+/// Number of differing bits of two Uuid fingerprints
+pub fn hamming_distance_uuid(a: Uuid, b: Uuid) -> i32 {
+    let a = u128::from_le_bytes(*a.as_bytes());
+    let b = u128::from_le_bytes(*b.as_bytes());
+    (a ^ b).count_ones() as i32
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pg_schema]
+mod tests {
+    use pg_row_hashes_fingerprint::{Algorithm, Farmhash, Seahash};
+    use pgrx::prelude::*;
+
+    use super::{shingles, simhash};
+
+    #[test]
+    fn test_shingles() {
+        assert_eq!(shingles("abcd", 3), ["abc", "bcd"]);
+        assert_eq!(shingles("äöü", 2), ["äö", "öü"]);
+        assert_eq!(shingles("ab", 3), ["ab"]);
+        assert!(shingles("", 3).is_empty());
+    }
+
+    #[test]
+    fn test_simhash_similarity() {
+        let sea = |t: &str| simhash::<64>(&shingles(t, 3), |s| Seahash::fingerprint(s).into());
+        let farm = |t: &str| simhash::<128>(&shingles(t, 3), Farmhash::fingerprint);
+
+        let a = "The quick brown fox jumps over the lazy dog";
+        let b = "The quick brown fox jumped over the lazy dog";
+        let c = "Lorem ipsum dolor sit amet, consectetur adipiscing elit";
+        assert!((sea(a) ^ sea(b)).count_ones() < (sea(a) ^ sea(c)).count_ones());
+        assert!((farm(a) ^ farm(b)).count_ones() < (farm(a) ^ farm(c)).count_ones());
+        assert_eq!(sea(""), 0);
+    }
+
+    #[pg_test]
+    fn pg_test_simhash() {
+        for (query, golden) in [
+            (
+                "SELECT hamming_distance(simhash_seahash('hello world'), simhash_seahash('hello world'));",
+                0,
+            ),
+            (
+                "SELECT hamming_distance(simhash_farmhash('hello world', 2), simhash_farmhash('hello world', 2));",
+                0,
+            ),
+            ("SELECT hamming_distance(0::bigint, -1::bigint);", 64),
+            (
+                "SELECT hamming_distance('00000000-0000-0000-0000-000000000000'::uuid, 'ffffffff-ffff-ffff-ffff-ffffffffffff'::uuid);",
+                128,
+            ),
+        ] {
+            let result = Spi::get_one::<i32>(query);
+            assert_eq!(result, Ok(Some(golden)), "using {query}");
+        }
+
+        let closer = Spi::get_one::<bool>(
+            "SELECT hamming_distance(simhash_farmhash(a), simhash_farmhash(b))
+                    < hamming_distance(simhash_farmhash(a), simhash_farmhash(c))
+             FROM (VALUES ('The quick brown fox jumps over the lazy dog',
+                           'The quick brown fox jumped over the lazy dog',
+                           'Lorem ipsum dolor sit amet, consectetur adipiscing elit')) t(a, b, c);",
+        );
+        assert_eq!(closer, Ok(Some(true)));
+    }
+}
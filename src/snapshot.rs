@@ -0,0 +1,595 @@
+use pgrx::prelude::*;
+
+/// Persisted table fingerprints.
+///
+/// Every snapshot is recorded in `row_hashes.snapshots` and stores the `bit_xor` of the
+/// `checksum_farmhash_record` of all rows, grouped by leaf partition and bucket (derived from the
+/// row fingerprint), together with the row count. Empty partitions have no buckets.
+#[pg_schema]
+pub mod row_hashes {
+    use pgrx::{prelude::*, spi, PgRelation, Uuid};
+
+    use crate::extension_schema;
+
+    /// Buckets of the first snapshot taken by `refresh`, the default of `snapshot`.
+    const DEFAULT_BUCKETS: i32 = 16;
+
+    extension_sql!(
+        r#"
+CREATE SEQUENCE row_hashes.snapshot_id_seq;
+
+CREATE TABLE row_hashes.snapshots (
+    snapshot_id bigint PRIMARY KEY,
+    relid oid NOT NULL,
+    relname text NOT NULL,
+    buckets int NOT NULL,
+    taken_at timestamptz NOT NULL DEFAULT now()
+);
+CREATE INDEX ON row_hashes.snapshots (relid, snapshot_id);
+
+CREATE TABLE row_hashes.fingerprints (
+    snapshot_id bigint NOT NULL,
+    relid oid NOT NULL,
+    relname text NOT NULL,
+    partition_relid oid NOT NULL,
+    partition text NOT NULL,
+    buckets int NOT NULL,
+    bucket int NOT NULL,
+    row_count bigint NOT NULL,
+    fingerprint uuid NOT NULL,
+    change_marker text,
+    taken_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (snapshot_id, partition_relid, bucket)
+);
+CREATE INDEX ON row_hashes.fingerprints (relid, snapshot_id);
+
+SELECT pg_catalog.pg_extension_config_dump('row_hashes.snapshots', '');
+SELECT pg_catalog.pg_extension_config_dump('row_hashes.fingerprints', '');
+SELECT pg_catalog.pg_extension_config_dump('row_hashes.snapshot_id_seq', '');
+"#,
+        name = "row_hashes_fingerprints",
+    );
+
+    extension_sql!(
+        r#"
+CREATE TABLE row_hashes.mismatches (
+    detected_at timestamptz NOT NULL DEFAULT now(),
+    relid oid NOT NULL,
+    relname text NOT NULL,
+    partition text NOT NULL,
+    bucket int NOT NULL,
+    status text NOT NULL,
+    expected_rows bigint,
+    actual_rows bigint,
+    expected uuid,
+    actual uuid
+);
+CREATE INDEX ON row_hashes.mismatches (relid, detected_at);
+
+SELECT pg_catalog.pg_extension_config_dump('row_hashes.mismatches', '');
+"#,
+        name = "row_hashes_mismatches",
+    );
+
+    #[pg_extern(immutable, strict, parallel_safe, create_or_replace)]
+    /// Bucket of a row fingerprint, in 0..buckets
+    pub fn bucket(fingerprint: Uuid, buckets: i32) -> i32 {
+        if buckets < 1 {
+            error!("buckets has to be positive, got {buckets}");
+        }
+        (u128::from_le_bytes(*fingerprint.as_bytes()) % buckets as u128) as i32
+    }
+
+    #[pg_extern(volatile, create_or_replace)]
+    /// Record the fingerprints of every leaf partition and bucket of a relation, returning the snapshot id.
+    ///
+    /// With `incremental`, partitions that have not been modified since the last snapshot according
+    /// to the cumulative statistics and their filenode keep their fingerprints. The statistics are
+    /// reported with a delay, can be reset and are not collected with `track_counts = off`, so
+    /// incremental snapshots can miss changes.
+    pub fn snapshot(
+        relation: PgRelation,
+        buckets: default!(i32, 16),
+        incremental: default!(bool, false),
+    ) -> Result<i64, spi::Error> {
+        if buckets < 1 {
+            error!("buckets has to be positive, got {buckets}");
+        }
+        let schema = extension_schema()?;
+        let relid = relation.oid();
+        let relname = crate::diff::qualified_name(&relation);
+
+        Spi::connect_mut(|client| {
+            let snapshot_id = client
+                .select("SELECT nextval('row_hashes.snapshot_id_seq')", None, &[])?
+                .first()
+                .get_one::<i64>()?
+                .expect("nextval returned NULL");
+            client.update(
+                "INSERT INTO row_hashes.snapshots (snapshot_id, relid, relname, buckets)
+                    VALUES ($1, $2, $3, $4)",
+                None,
+                &[
+                    snapshot_id.into(),
+                    relid.into(),
+                    relname.clone().into(),
+                    buckets.into(),
+                ],
+            )?;
+
+            for (partition_relid, partition, change_marker) in leaf_partitions(client, relid)? {
+                if incremental {
+                    let reused = client.update(
+                        "INSERT INTO row_hashes.fingerprints
+                            (snapshot_id, relid, relname, partition_relid, partition, buckets, bucket, row_count, fingerprint, change_marker)
+                        SELECT $1, relid, $2, partition_relid, $3, buckets, bucket, row_count, fingerprint, change_marker
+                        FROM row_hashes.fingerprints
+                        WHERE snapshot_id = (SELECT max(snapshot_id) FROM row_hashes.snapshots WHERE relid = $4 AND snapshot_id < $1)
+                            AND partition_relid = $5 AND buckets = $6 AND change_marker = $7
+                        RETURNING bucket",
+                        None,
+                        &[
+                            snapshot_id.into(),
+                            relname.clone().into(),
+                            partition.clone().into(),
+                            relid.into(),
+                            partition_relid.into(),
+                            buckets.into(),
+                            change_marker.clone().into(),
+                        ],
+                    )?;
+                    if !reused.is_empty() {
+                        continue;
+                    }
+                }
+
+                client.update(
+                    &format!(
+                        "INSERT INTO row_hashes.fingerprints
+                            (snapshot_id, relid, relname, partition_relid, partition, buckets, bucket, row_count, fingerprint, change_marker)
+                        SELECT $1, $2, $3, $4, $5, $6, b.bucket, b.row_count, b.fingerprint, $7
+                        FROM ({}) b",
+                        bucket_fingerprints_query(&schema, &partition, buckets)
+                    ),
+                    None,
+                    &[
+                        snapshot_id.into(),
+                        relid.into(),
+                        relname.clone().into(),
+                        partition_relid.into(),
+                        partition.into(),
+                        buckets.into(),
+                        change_marker.into(),
+                    ],
+                )?;
+            }
+            Ok(snapshot_id)
+        })
+    }
+
+    #[pg_extern(stable, create_or_replace)]
+    /// Compare the current fingerprints of a relation with its last snapshot.
+    ///
+    /// Status is one of `ok`, `changed`, `new` (bucket not in the snapshot) or `missing` (bucket
+    /// only in the snapshot).
+    pub fn verify(
+        relation: PgRelation,
+    ) -> Result<
+        TableIterator<
+            'static,
+            (
+                name!(partition, String),
+                name!(bucket, i32),
+                name!(status, String),
+                name!(expected_rows, Option<i64>),
+                name!(actual_rows, Option<i64>),
+                name!(expected, Option<Uuid>),
+                name!(actual, Option<Uuid>),
+            ),
+        >,
+        spi::Error,
+    > {
+        let schema = extension_schema()?;
+        let relid = relation.oid();
+
+        let rows = Spi::connect(|client| {
+            let last = client
+                .select(
+                    "SELECT snapshot_id, buckets FROM row_hashes.snapshots
+                        WHERE relid = $1 ORDER BY snapshot_id DESC LIMIT 1",
+                    None,
+                    &[relid.into()],
+                )?
+                .first()
+                .get_two::<i64, i32>()?;
+            let (Some(snapshot_id), Some(buckets)) = last else {
+                error!("no snapshot of {} found", relation.name());
+            };
+
+            let partitions = leaf_partitions(client, relid)?;
+            if partitions.is_empty() {
+                // a partitioned table without partitions, every bucket of the snapshot is missing
+                return client
+                    .select(
+                        "SELECT partition_relid::regclass::text, bucket, 'missing', row_count, NULL::bigint,
+                            fingerprint, NULL::uuid
+                        FROM row_hashes.fingerprints WHERE snapshot_id = $1
+                        ORDER BY 1, 2",
+                        None,
+                        &[snapshot_id.into()],
+                    )?
+                    .map(verify_row)
+                    .collect::<Result<Vec<_>, spi::Error>>();
+            }
+            let current = partitions
+                .into_iter()
+                .map(|(partition_relid, partition, _)| {
+                    format!(
+                        "SELECT {}::oid AS partition_relid, b.* FROM ({}) b",
+                        partition_relid.to_u32(),
+                        bucket_fingerprints_query(&schema, &partition, buckets)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(" UNION ALL ");
+
+            client
+                .select(
+                    &format!(
+                        "WITH last AS (
+                            SELECT partition_relid, bucket, row_count, fingerprint
+                            FROM row_hashes.fingerprints WHERE snapshot_id = $1
+                        ), current AS ({current})
+                        SELECT coalesce(c.partition_relid, l.partition_relid)::regclass::text,
+                            coalesce(c.bucket, l.bucket),
+                            CASE
+                                WHEN l.bucket IS NULL THEN 'new'
+                                WHEN c.bucket IS NULL THEN 'missing'
+                                WHEN l.row_count = c.row_count AND l.fingerprint = c.fingerprint THEN 'ok'
+                                ELSE 'changed'
+                            END,
+                            l.row_count, c.row_count, l.fingerprint, c.fingerprint
+                        FROM current c FULL OUTER JOIN last l
+                            ON c.partition_relid = l.partition_relid AND c.bucket = l.bucket
+                        ORDER BY 1, 2"
+                    ),
+                    None,
+                    &[snapshot_id.into()],
+                )?
+                .map(verify_row)
+                .collect::<Result<Vec<_>, spi::Error>>()
+        })?;
+        Ok(TableIterator::new(rows))
+    }
+
+    /// Row of `verify`.
+    type VerifyRow = (
+        String,
+        i32,
+        String,
+        Option<i64>,
+        Option<i64>,
+        Option<Uuid>,
+        Option<Uuid>,
+    );
+
+    fn verify_row(row: spi::SpiHeapTupleData<'_>) -> Result<VerifyRow, spi::Error> {
+        Ok((
+            row.get::<String>(1)?.unwrap_or_default(),
+            row.get::<i32>(2)?.unwrap_or_default(),
+            row.get::<String>(3)?.unwrap_or_default(),
+            row.get::<i64>(4)?,
+            row.get::<i64>(5)?,
+            row.get::<Uuid>(6)?,
+            row.get::<Uuid>(7)?,
+        ))
+    }
+
+    #[pg_extern(volatile, create_or_replace)]
+    /// Record the differences to the last snapshot of a relation in `row_hashes.mismatches`, then
+    /// take a new snapshot. Returns the number of mismatches.
+    ///
+    /// The first refresh of a relation only takes a snapshot with the default number of buckets.
+    pub fn refresh(relation: PgRelation) -> Result<i64, spi::Error> {
+        let relid = relation.oid();
+        let relname = crate::diff::qualified_name(&relation);
+
+        let Some(buckets) = Spi::get_one_with_args::<i32>(
+            "SELECT (SELECT buckets FROM row_hashes.snapshots WHERE relid = $1
+                ORDER BY snapshot_id DESC LIMIT 1)",
+            &[relid.into()],
+        )?
+        else {
+            snapshot(relation, DEFAULT_BUCKETS, false)?;
+            return Ok(0);
+        };
+
+        let mismatches = Spi::connect_mut(|client| {
+            let inserted = client.update(
+                "INSERT INTO row_hashes.mismatches
+                    (relid, relname, partition, bucket, status, expected_rows, actual_rows, expected, actual)
+                SELECT $1, $2, v.* FROM row_hashes.verify($1) v WHERE v.status <> 'ok'
+                RETURNING bucket",
+                None,
+                &[relid.into(), relname.into()],
+            )?;
+            Ok(inserted.len() as i64)
+        })?;
+
+        snapshot(relation, buckets, false)?;
+        Ok(mismatches)
+    }
+
+    /// Leaf partitions of `relid` (the relation itself if it is not partitioned) with a marker
+    /// that changes whenever the partition has been modified.
+    fn leaf_partitions(
+        client: &spi::SpiClient<'_>,
+        relid: pg_sys::Oid,
+    ) -> Result<Vec<(pg_sys::Oid, String, String)>, spi::Error> {
+        client
+            .select(
+                "SELECT p.relid, p.relid::regclass::text,
+                    concat_ws(':', pg_relation_filenode(p.relid),
+                        pg_stat_get_tuples_inserted(p.relid)
+                        + pg_stat_get_tuples_updated(p.relid)
+                        + pg_stat_get_tuples_deleted(p.relid)
+                        + pg_stat_get_xact_tuples_inserted(p.relid)
+                        + pg_stat_get_xact_tuples_updated(p.relid)
+                        + pg_stat_get_xact_tuples_deleted(p.relid))
+                FROM pg_partition_tree($1) p
+                WHERE p.isleaf
+                ORDER BY 1",
+                None,
+                &[relid.into()],
+            )?
+            .map(|row| {
+                Ok((
+                    row.get::<pg_sys::Oid>(1)?.expect("relid is not NULL"),
+                    row.get::<String>(2)?.expect("relid is not NULL"),
+                    row.get::<String>(3)?.unwrap_or_default(),
+                ))
+            })
+            .collect()
+    }
+
+    /// Query of `(bucket, row_count, fingerprint)` of a single partition.
+    fn bucket_fingerprints_query(schema: &str, partition: &str, buckets: i32) -> String {
+        let schema = spi::quote_identifier(schema);
+        format!(
+            "SELECT row_hashes.bucket(h, {buckets}) AS bucket, count(*) AS row_count, {schema}.bit_xor(h) AS fingerprint
+            FROM (SELECT {schema}.checksum_farmhash_record(t) AS h FROM ONLY {partition} t) r
+            GROUP BY 1"
+        )
+    }
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pg_schema]
+mod tests {
+    use pgrx::prelude::*;
+
+    fn verify_statuses(relation: &str) -> Vec<(String, i32, String)> {
+        Spi::connect(|client| {
+            client
+                .select(
+                    &format!(
+                        "SELECT partition, bucket, status FROM row_hashes.verify('{relation}');"
+                    ),
+                    None,
+                    &[],
+                )?
+                .map(|row| {
+                    Ok((
+                        row.get::<String>(1)?.expect("got None"),
+                        row.get::<i32>(2)?.expect("got None"),
+                        row.get::<String>(3)?.expect("got None"),
+                    ))
+                })
+                .collect::<Result<Vec<_>, pgrx::spi::Error>>()
+        })
+        .expect("SPI failed")
+    }
+
+    #[pg_test]
+    fn pg_test_bucket() {
+        for buckets in [1, 2, 16, 255] {
+            let result = Spi::get_one::<i32>(&format!(
+                "SELECT row_hashes.bucket(id_farmhash('a'), {buckets});"
+            ))
+            .expect("didn't get SPI result")
+            .expect("got None");
+            assert!((0..buckets).contains(&result));
+        }
+    }
+
+    #[pg_test]
+    fn pg_test_snapshot_verify() {
+        Spi::run(
+            "CREATE TABLE snapshot_test (id int, name text);
+             INSERT INTO snapshot_test SELECT i, i::text FROM generate_series(1, 100) i;",
+        )
+        .expect("SPI failed");
+
+        let snapshot_id = Spi::get_one::<i64>("SELECT row_hashes.snapshot('snapshot_test', 4);")
+            .expect("didn't get SPI result")
+            .expect("got None");
+        let (rows, buckets) = Spi::get_two::<i64, i64>(&format!(
+            "SELECT sum(row_count)::bigint, count(*) FROM row_hashes.fingerprints WHERE snapshot_id = {snapshot_id};"
+        ))
+        .expect("didn't get SPI result");
+        assert_eq!(rows, Some(100));
+        assert_eq!(buckets, Some(4));
+
+        let statuses = verify_statuses("snapshot_test");
+        assert_eq!(statuses.len(), 4);
+        assert!(statuses.iter().all(|(_, _, status)| status == "ok"));
+
+        Spi::run("UPDATE snapshot_test SET name = 'changed' WHERE id = 1;").expect("SPI failed");
+        let changed: Vec<_> = verify_statuses("snapshot_test")
+            .into_iter()
+            .filter(|(_, _, status)| status != "ok")
+            .collect();
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].2, "changed");
+    }
+
+    #[pg_test]
+    fn pg_test_snapshot_empty() {
+        Spi::run(
+            "CREATE TABLE snapshot_empty (id int);
+             CREATE TABLE snapshot_no_partitions (id int) PARTITION BY RANGE (id);
+             SELECT row_hashes.snapshot('snapshot_empty');
+             SELECT row_hashes.snapshot('snapshot_no_partitions');",
+        )
+        .expect("SPI failed");
+        assert_eq!(verify_statuses("snapshot_empty"), vec![]);
+        assert_eq!(verify_statuses("snapshot_no_partitions"), vec![]);
+
+        Spi::run("INSERT INTO snapshot_empty VALUES (1);").expect("SPI failed");
+        let statuses = verify_statuses("snapshot_empty");
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].2, "new");
+
+        let refreshed = Spi::get_one::<i64>("SELECT row_hashes.refresh('snapshot_no_partitions');");
+        assert_eq!(refreshed, Ok(Some(0)));
+    }
+
+    #[pg_test]
+    fn pg_test_refresh() {
+        Spi::run(
+            "CREATE TABLE refresh_test (id int, name text);
+             INSERT INTO refresh_test SELECT i, i::text FROM generate_series(1, 10) i;",
+        )
+        .expect("SPI failed");
+
+        let refresh = || {
+            Spi::get_one::<i64>("SELECT row_hashes.refresh('refresh_test');")
+                .expect("didn't get SPI result")
+                .expect("got None")
+        };
+        assert_eq!(refresh(), 0);
+        assert_eq!(refresh(), 0);
+
+        Spi::run("INSERT INTO refresh_test VALUES (11, '11');").expect("SPI failed");
+        assert_eq!(refresh(), 1);
+        assert_eq!(refresh(), 0);
+
+        let recorded = Spi::get_one::<i64>(
+            "SELECT count(*) FROM row_hashes.mismatches WHERE relid = 'refresh_test'::regclass;",
+        )
+        .expect("didn't get SPI result");
+        assert_eq!(recorded, Some(1));
+    }
+
+    #[pg_test]
+    fn pg_test_snapshot_partitioned() {
+        Spi::run(
+            "CREATE TABLE snapshot_parted (id int, name text) PARTITION BY RANGE (id);
+             CREATE TABLE snapshot_parted_1 PARTITION OF snapshot_parted FOR VALUES FROM (0) TO (50);
+             CREATE TABLE snapshot_parted_2 PARTITION OF snapshot_parted FOR VALUES FROM (50) TO (100);
+             INSERT INTO snapshot_parted SELECT i, i::text FROM generate_series(0, 99) i;",
+        )
+        .expect("SPI failed");
+
+        let snapshot_id = Spi::get_one::<i64>("SELECT row_hashes.snapshot('snapshot_parted', 1);")
+            .expect("didn't get SPI result")
+            .expect("got None");
+        let partitions = Spi::get_one::<i64>(&format!(
+            "SELECT count(DISTINCT partition_relid) FROM row_hashes.fingerprints WHERE snapshot_id = {snapshot_id};"
+        ))
+        .expect("didn't get SPI result");
+        assert_eq!(partitions, Some(2));
+
+        Spi::run("DELETE FROM snapshot_parted WHERE id = 75;").expect("SPI failed");
+        let statuses = verify_statuses("snapshot_parted");
+        assert_eq!(
+            statuses
+                .iter()
+                .map(|(partition, _, status)| (partition.as_str(), status.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("snapshot_parted_1", "ok"),
+                ("snapshot_parted_2", "changed")
+            ]
+        );
+
+        // only the changed partition is fingerprinted again
+        Spi::run("SELECT row_hashes.snapshot('snapshot_parted', 1, incremental => true);")
+            .expect("SPI failed");
+        assert!(verify_statuses("snapshot_parted")
+            .iter()
+            .all(|(_, _, status)| status == "ok"));
+    }
+
+    #[pg_test]
+    fn pg_test_snapshot_incremental() {
+        Spi::run(
+            "CREATE TABLE snapshot_incremental (id int, name text) PARTITION BY RANGE (id);
+             CREATE TABLE snapshot_incremental_1 PARTITION OF snapshot_incremental FOR VALUES FROM (0) TO (10);
+             CREATE TABLE snapshot_incremental_2 PARTITION OF snapshot_incremental FOR VALUES FROM (10) TO (20);
+             CREATE TABLE snapshot_incremental_3 PARTITION OF snapshot_incremental FOR VALUES FROM (20) TO (30);
+             INSERT INTO snapshot_incremental SELECT i, i::text FROM generate_series(0, 29) i;",
+        )
+        .expect("SPI failed");
+
+        // a single bucket per partition, which is the unit an incremental snapshot reuses
+        let first = Spi::get_one::<i64>("SELECT row_hashes.snapshot('snapshot_incremental', 1);")
+            .expect("didn't get SPI result")
+            .expect("got None");
+        // fingerprints only a copy can have
+        Spi::run(&format!(
+            "UPDATE row_hashes.fingerprints
+                SET fingerprint = '00000000-0000-0000-0000-000000000000', row_count = -1
+                WHERE snapshot_id = {first};"
+        ))
+        .expect("SPI failed");
+
+        Spi::run("UPDATE snapshot_incremental SET name = 'changed' WHERE id = 15;")
+            .expect("SPI failed");
+        let second = Spi::get_one::<i64>(
+            "SELECT row_hashes.snapshot('snapshot_incremental', 1, incremental => true);",
+        )
+        .expect("didn't get SPI result")
+        .expect("got None");
+
+        let copied = Spi::connect(|client| {
+            client
+                .select(
+                    &format!(
+                        "SELECT s.partition,
+                            s.row_count = -1 AND s.fingerprint = '00000000-0000-0000-0000-000000000000',
+                            s.change_marker = f.change_marker
+                        FROM row_hashes.fingerprints s
+                            JOIN row_hashes.fingerprints f USING (partition_relid, bucket)
+                        WHERE s.snapshot_id = {second} AND f.snapshot_id = {first}
+                        ORDER BY 1;"
+                    ),
+                    None,
+                    &[],
+                )?
+                .map(|row| {
+                    Ok((
+                        row.get::<String>(1)?.expect("got None"),
+                        row.get::<bool>(2)?.expect("got None"),
+                        row.get::<bool>(3)?.expect("got None"),
+                    ))
+                })
+                .collect::<Result<Vec<_>, pgrx::spi::Error>>()
+        })
+        .expect("SPI failed");
+        assert_eq!(
+            copied,
+            vec![
+                ("snapshot_incremental_1".to_string(), true, true),
+                ("snapshot_incremental_2".to_string(), false, false),
+                ("snapshot_incremental_3".to_string(), true, true),
+            ]
+        );
+
+        let recomputed = Spi::get_one::<i64>(&format!(
+            "SELECT row_count FROM row_hashes.fingerprints
+                WHERE snapshot_id = {second} AND partition = 'snapshot_incremental_2';"
+        ));
+        assert_eq!(recomputed, Ok(Some(10)));
+    }
+}
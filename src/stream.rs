@@ -0,0 +1,151 @@
+//! Hashing of `bytea` and `text` values in slices, so that values stored out of line
+//! uncompressed (storage `EXTERNAL`) are never detoasted as a whole.
+//!
+//! The functions take the raw datum, so they are defined in SQL below instead of with
+//! `#[pg_extern]`.
+
+use std::hash::Hasher;
+
+use pgrx::{pg_sys, prelude::*, IntoDatum};
+
+/// Bytes detoasted at once.
+const SLICE_SIZE: usize = 1 << 20;
+
+extension_sql!(
+    r#"
+CREATE OR REPLACE FUNCTION stream_seahash(bytea) RETURNS bigint
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', 'stream_seahash';
+CREATE OR REPLACE FUNCTION stream_seahash(text) RETURNS bigint
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', 'stream_seahash';
+CREATE OR REPLACE FUNCTION stream_xxh3(bytea) RETURNS bigint
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', 'stream_xxh3';
+CREATE OR REPLACE FUNCTION stream_xxh3(text) RETURNS bigint
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', 'stream_xxh3';
+CREATE OR REPLACE FUNCTION stream_blake3(bytea) RETURNS bytea
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', 'stream_blake3';
+CREATE OR REPLACE FUNCTION stream_blake3(text) RETURNS bytea
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', 'stream_blake3';
+"#,
+    name = "stream_functions",
+);
+
+/// Feed the bytes of a varlena datum to `f` in slices of at most [`SLICE_SIZE`] bytes.
+///
+/// Only uncompressed out of line values are fetched slice by slice. Compressed values can only
+/// be decompressed from their start and inline values are small, so those are detoasted at once.
+unsafe fn for_each_slice(datum: pg_sys::Datum, mut f: impl FnMut(&[u8])) {
+    unsafe {
+        let ptr = datum.cast_mut_ptr::<pg_sys::varlena>();
+        let raw_size = pg_sys::toast_raw_datum_size(datum) - pg_sys::VARHDRSZ;
+        let uncompressed_external =
+            pgrx::varlena::varatt_is_1b_e(ptr) && pg_sys::toast_datum_size(datum) >= raw_size;
+
+        if !uncompressed_external {
+            let detoasted = pg_sys::pg_detoast_datum_packed(ptr);
+            f(pgrx::varlena::varlena_to_byte_slice(detoasted));
+            if detoasted != ptr {
+                pg_sys::pfree(detoasted.cast());
+            }
+            return;
+        }
+
+        for offset in (0..raw_size).step_by(SLICE_SIZE) {
+            let len = SLICE_SIZE.min(raw_size - offset);
+            let slice = pg_sys::detoast_attr_slice(ptr, offset as i32, len as i32);
+            f(pgrx::varlena::varlena_to_byte_slice(slice));
+            pg_sys::pfree(slice.cast());
+        }
+    }
+}
+
+pg_finfo_v1!(
+    pg_finfo_stream_seahash,
+    pg_finfo_stream_xxh3,
+    pg_finfo_stream_blake3
+);
+
+#[pg_guard]
+#[unsafe(no_mangle)]
+/// seahash of a `bytea` or `text`, the same as `id_seahash` of it.
+pub unsafe extern "C-unwind" fn stream_seahash(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+    let mut hasher = seahash::SeaHasher::new();
+    unsafe {
+        for_each_slice(pgrx::fcinfo::pg_getarg_datum_raw(fcinfo, 0), |s| {
+            hasher.write(s)
+        })
+    };
+    pg_sys::Datum::from(hasher.finish() as i64)
+}
+
+#[pg_guard]
+#[unsafe(no_mangle)]
+/// XXH3 (64 bit) of a `bytea` or `text`.
+pub unsafe extern "C-unwind" fn stream_xxh3(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+    let mut hasher = xxhash_rust::xxh3::Xxh3::new();
+    unsafe {
+        for_each_slice(pgrx::fcinfo::pg_getarg_datum_raw(fcinfo, 0), |s| {
+            hasher.update(s)
+        })
+    };
+    pg_sys::Datum::from(hasher.digest() as i64)
+}
+
+#[pg_guard]
+#[unsafe(no_mangle)]
+/// BLAKE3 (256 bit) of a `bytea` or `text`.
+pub unsafe extern "C-unwind" fn stream_blake3(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+    let mut hasher = blake3::Hasher::new();
+    unsafe {
+        for_each_slice(pgrx::fcinfo::pg_getarg_datum_raw(fcinfo, 0), |s| {
+            hasher.update(s);
+        })
+    };
+    hasher
+        .finalize()
+        .as_bytes()
+        .to_vec()
+        .into_datum()
+        .expect("bytea is not NULL")
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pg_schema]
+mod tests {
+    use pgrx::prelude::*;
+
+    #[pg_test]
+    fn pg_test_stream_hashes() {
+        for (query, golden) in [
+            (
+                "SELECT stream_xxh3('abc'::bytea)::text;",
+                "8696274497037089104",
+            ),
+            (
+                "SELECT encode(stream_blake3('abc'::text), 'hex');",
+                "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85",
+            ),
+        ] {
+            let result = Spi::get_one::<String>(query);
+            assert_eq!(result, Ok(Some(golden.to_string())), "using {query}");
+        }
+    }
+
+    #[pg_test]
+    fn pg_test_stream_seahash_toasted() {
+        Spi::run(
+            "CREATE TABLE stream_test (id int, b bytea, t text);
+             ALTER TABLE stream_test ALTER COLUMN b SET STORAGE EXTERNAL;
+             INSERT INTO stream_test
+                SELECT 1, v::bytea, v FROM (SELECT string_agg(md5(i::text), '') AS v FROM generate_series(1, 100000) i) s;
+             INSERT INTO stream_test VALUES (2, 'abc', 'abc'), (3, '', '');",
+        )
+        .expect("SPI failed");
+
+        let result = Spi::get_one::<bool>(
+            "SELECT bool_and(stream_seahash(b) = id_seahash(b) AND stream_seahash(t) = id_seahash(t)
+                AND stream_xxh3(b) = stream_xxh3(t) AND stream_blake3(b) = stream_blake3(t))
+             FROM stream_test;",
+        );
+        assert_eq!(result, Ok(Some(true)));
+    }
+}
@@ -0,0 +1,269 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use pg_row_hashes_fingerprint::{checksum_bytes, NullPolicy};
+use pgrx::{prelude::*, PgHeapTupleError, PgTriggerError, WhoAllocated};
+
+use crate::record::{tuple_pairs, ColumnFilter};
+use crate::{farmhash_fingerprint, seahash_fingerprint, Algorithm};
+
+/// Configuration of `row_hash_trigger()`, parsed from the trigger arguments.
+///
+/// Every argument has the form `key=value`:
+///  * `column`: the column to store the fingerprint in (required),
+///  * `algorithm`: `farmhash` (`uuid` column, default) or `seahash` (`bigint` column),
+///  * `extendable`: `true` to skip `NULL` values like `checksum_*_extendable`, default `false`,
+///  * `include`: comma-separated list of the only columns to fingerprint,
+///  * `exclude`: comma-separated list of columns not to fingerprint,
+///  * `include_pattern`, `exclude_pattern`: regular expressions on the column names.
+///
+/// The fingerprint column itself is always excluded.
+#[derive(Debug)]
+struct TriggerOptions {
+    column: String,
+    algorithm: Algorithm,
+    nulls: NullPolicy,
+    filter: ColumnFilter,
+}
+
+/// Parsed options of triggers are kept for reuse up to this many.
+const OPTIONS_CACHE_SIZE: usize = 64;
+
+thread_local! {
+    /// Parsed options by trigger arguments, so every trigger is only parsed once per backend.
+    static OPTIONS: RefCell<HashMap<Vec<String>, Rc<TriggerOptions>>> = RefCell::default();
+}
+
+impl TriggerOptions {
+    /// Like [`TriggerOptions::parse`], but reuses the options of a trigger with the same arguments.
+    fn cached(args: Vec<String>) -> Result<Rc<Self>, RowHashTriggerError> {
+        OPTIONS.with_borrow_mut(|cache| {
+            if let Some(options) = cache.get(&args) {
+                return Ok(Rc::clone(options));
+            }
+            let options = Rc::new(Self::parse(&args)?);
+            if cache.len() >= OPTIONS_CACHE_SIZE {
+                cache.clear();
+            }
+            cache.insert(args, Rc::clone(&options));
+            Ok(options)
+        })
+    }
+
+    fn parse(args: &[String]) -> Result<Self, RowHashTriggerError> {
+        let mut column = None;
+        let mut algorithm = Algorithm::default();
+        let mut nulls = NullPolicy::Keep;
+        let mut include = None;
+        let mut exclude = Vec::new();
+        let mut include_pattern = None;
+        let mut exclude_pattern = None;
+
+        for arg in args {
+            let (key, value) = arg
+                .split_once('=')
+                .ok_or_else(|| RowHashTriggerError::Argument(arg.clone()))?;
+            let value = value.trim();
+            match key.trim() {
+                "column" => column = Some(value.to_string()),
+                "algorithm" => algorithm = value.parse().map_err(RowHashTriggerError::Option)?,
+                "extendable" => {
+                    let extendable: bool = value
+                        .parse()
+                        .map_err(|_| RowHashTriggerError::Argument(arg.clone()))?;
+                    nulls = if extendable {
+                        NullPolicy::Skip
+                    } else {
+                        NullPolicy::Keep
+                    };
+                }
+                "include" => include = Some(split_columns(value)),
+                "exclude" => exclude.extend(split_columns(value)),
+                "include_pattern" => include_pattern = Some(value),
+                "exclude_pattern" => exclude_pattern = Some(value),
+                _ => return Err(RowHashTriggerError::Argument(arg.clone())),
+            }
+        }
+
+        let column = column.ok_or(RowHashTriggerError::MissingColumn)?;
+        let mut filter = ColumnFilter::new(include, exclude)
+            .with_patterns(include_pattern, exclude_pattern)
+            .map_err(|e| RowHashTriggerError::Option(e.to_string()))?;
+        filter.exclude(column.clone());
+
+        Ok(TriggerOptions {
+            column,
+            algorithm,
+            nulls,
+            filter,
+        })
+    }
+}
+
+fn split_columns(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+#[derive(Debug)]
+enum RowHashTriggerError {
+    Argument(String),
+    Option(String),
+    MissingColumn,
+    NotBeforeRow,
+    Trigger(PgTriggerError),
+    HeapTuple(PgHeapTupleError),
+}
+
+impl fmt::Display for RowHashTriggerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RowHashTriggerError::Argument(arg) => write!(f, "invalid trigger argument \"{arg}\""),
+            RowHashTriggerError::Option(msg) => f.write_str(msg),
+            RowHashTriggerError::MissingColumn => {
+                f.write_str("row_hash_trigger() requires a column=<name> argument")
+            }
+            RowHashTriggerError::NotBeforeRow => {
+                f.write_str("row_hash_trigger() must be fired BEFORE INSERT OR UPDATE FOR EACH ROW")
+            }
+            RowHashTriggerError::Trigger(e) => e.fmt(f),
+            RowHashTriggerError::HeapTuple(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for RowHashTriggerError {}
+
+impl From<PgTriggerError> for RowHashTriggerError {
+    fn from(e: PgTriggerError) -> Self {
+        RowHashTriggerError::Trigger(e)
+    }
+}
+
+impl From<PgHeapTupleError> for RowHashTriggerError {
+    fn from(e: PgHeapTupleError) -> Self {
+        RowHashTriggerError::HeapTuple(e)
+    }
+}
+
+#[pg_trigger]
+/// Store the fingerprint of the new row in a column, configured by the trigger arguments.
+fn row_hash_trigger<'a>(
+    trigger: &'a PgTrigger<'a>,
+) -> Result<Option<PgHeapTuple<'a, impl WhoAllocated>>, RowHashTriggerError> {
+    if trigger.when()? != PgTriggerWhen::Before || trigger.level() != PgTriggerLevel::Row {
+        return Err(RowHashTriggerError::NotBeforeRow);
+    }
+    let options = TriggerOptions::cached(trigger.extra_args()?)?;
+
+    let new = trigger.new().ok_or(RowHashTriggerError::NotBeforeRow)?;
+    let relation = trigger.relation()?;
+    let tupdesc = relation.tuple_desc();
+    let pairs = unsafe { tuple_pairs(new.into_pg(), &tupdesc, &options.filter) };
+    let bytes = checksum_bytes(pairs, options.nulls);
+
+    let mut new = trigger
+        .new()
+        .ok_or(RowHashTriggerError::NotBeforeRow)?
+        .into_owned();
+    match options.algorithm {
+        Algorithm::Farmhash => new.set_by_name(&options.column, farmhash_fingerprint(bytes))?,
+        Algorithm::Seahash => new.set_by_name(&options.column, seahash_fingerprint(bytes))?,
+    }
+    Ok(Some(new))
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pg_schema]
+mod tests {
+    use pgrx::prelude::*;
+    use pgrx::Uuid;
+
+    use pg_row_hashes_fingerprint::NullPolicy;
+
+    use super::TriggerOptions;
+    use crate::Algorithm;
+
+    #[test]
+    fn test_trigger_options() {
+        let args = [
+            "column=row_hash",
+            "algorithm=seahash",
+            "exclude=updated_at, id",
+        ]
+        .map(String::from);
+        let options = TriggerOptions::parse(&args).expect("valid options");
+        assert_eq!(options.column, "row_hash");
+        assert_eq!(options.algorithm, Algorithm::Seahash);
+        assert_eq!(options.nulls, NullPolicy::Keep);
+        assert!(!options.filter.matches("row_hash"));
+        assert!(!options.filter.matches("id"));
+        assert!(options.filter.matches("name"));
+
+        assert!(TriggerOptions::parse(&["algorithm=farmhash".to_string()]).is_err());
+        assert!(TriggerOptions::parse(&["column".to_string()]).is_err());
+        assert!(TriggerOptions::parse(&["column=a".into(), "algorithm=md5".into()]).is_err());
+
+        let cached = TriggerOptions::cached(args.to_vec()).expect("valid options");
+        assert!(std::rc::Rc::ptr_eq(
+            &cached,
+            &TriggerOptions::cached(args.to_vec()).expect("valid options")
+        ));
+        assert!(TriggerOptions::cached(vec!["column".to_string()]).is_err());
+    }
+
+    #[pg_test]
+    fn pg_test_row_hash_trigger() {
+        Spi::run(
+            "CREATE TABLE trigger_test (id int, name text, note text, updated_at timestamptz, row_hash uuid);
+             CREATE TRIGGER trigger_test_row_hash BEFORE INSERT OR UPDATE ON trigger_test
+                FOR EACH ROW EXECUTE FUNCTION row_hash_trigger('column=row_hash', 'exclude=updated_at');
+             INSERT INTO trigger_test (id, name, note, updated_at) VALUES (1, 'a', NULL, now());",
+        )
+        .expect("SPI failed");
+
+        let result = Spi::get_one::<Uuid>("SELECT row_hash FROM trigger_test;")
+            .expect("didn't get SPI result")
+            .expect("got None");
+        let golden =
+            Spi::get_one::<Uuid>("SELECT checksum_farmhash('id', '1', 'name', 'a', 'note', NULL);")
+                .expect("didn't get SPI result")
+                .expect("got None");
+        assert_eq!(result, golden);
+
+        Spi::run("UPDATE trigger_test SET name = 'b', updated_at = now();").expect("SPI failed");
+        let result = Spi::get_one::<Uuid>("SELECT row_hash FROM trigger_test;")
+            .expect("didn't get SPI result")
+            .expect("got None");
+        let golden =
+            Spi::get_one::<Uuid>("SELECT checksum_farmhash('id', '1', 'name', 'b', 'note', NULL);")
+                .expect("didn't get SPI result")
+                .expect("got None");
+        assert_eq!(result, golden);
+    }
+
+    #[pg_test]
+    fn pg_test_row_hash_trigger_seahash_extendable() {
+        Spi::run(
+            "CREATE TABLE trigger_test (id int, name text, note text, row_hash bigint);
+             CREATE TRIGGER trigger_test_row_hash BEFORE INSERT ON trigger_test
+                FOR EACH ROW EXECUTE FUNCTION row_hash_trigger('column=row_hash', 'algorithm=seahash', 'extendable=true', 'include=id,note');
+             INSERT INTO trigger_test (id, name, note) VALUES (1, 'a', NULL);",
+        )
+        .expect("SPI failed");
+
+        let result = Spi::get_one::<i64>("SELECT row_hash FROM trigger_test;")
+            .expect("didn't get SPI result")
+            .expect("got None");
+        let golden = Spi::get_one::<i64>("SELECT checksum_seahash_extendable('id', '1');")
+            .expect("didn't get SPI result")
+            .expect("got None");
+        assert_eq!(result, golden);
+    }
+}
@@ -0,0 +1,522 @@
+//! The `fingerprint64` and `fingerprint128` types, seahash and farmhash fingerprints that neither
+//! look like numbers nor identifiers.
+//!
+//! They are stored exactly like `bigint` and `uuid` and are binary coercible to them, so the
+//! binary protocol, equality and hashing use PostgreSQL's functions of those types. The text
+//! representation, the hexadecimal of `pg_row_hashes_fingerprint::hex`, the order and `bytea`
+//! casts are the ones of the unsigned integer instead: ordered like the hexadecimal, cast to its
+//! bytes in network byte order (those of `int8send`, but the reverse of `uuid_send`). These take
+//! raw datums, so everything is defined in SQL instead of with `#[pg_extern]`.
+
+use std::cmp::Ordering;
+use std::ffi::{CStr, CString};
+
+use pg_row_hashes_fingerprint::hex;
+use pgrx::{fcinfo::pg_getarg, pg_sys, prelude::*, FromDatum, IntoDatum, Uuid};
+
+use crate::{farmhash_uuid, seahash_bigint};
+
+extension_sql!(
+    r#"
+CREATE TYPE fingerprint64;
+CREATE FUNCTION fingerprint64_in(cstring) RETURNS fingerprint64
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', 'fingerprint64_in';
+CREATE FUNCTION fingerprint64_out(fingerprint64) RETURNS cstring
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', 'fingerprint64_out';
+CREATE FUNCTION fingerprint64_recv(internal) RETURNS fingerprint64
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE internal AS 'int8recv';
+CREATE FUNCTION fingerprint64_send(fingerprint64) RETURNS bytea
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE internal AS 'int8send';
+CREATE TYPE fingerprint64 (
+    INPUT = fingerprint64_in,
+    OUTPUT = fingerprint64_out,
+    RECEIVE = fingerprint64_recv,
+    SEND = fingerprint64_send,
+    INTERNALLENGTH = 8,
+    PASSEDBYVALUE,
+    ALIGNMENT = double,
+    STORAGE = plain
+);
+
+CREATE TYPE fingerprint128;
+CREATE FUNCTION fingerprint128_in(cstring) RETURNS fingerprint128
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', 'fingerprint128_in';
+CREATE FUNCTION fingerprint128_out(fingerprint128) RETURNS cstring
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', 'fingerprint128_out';
+CREATE FUNCTION fingerprint128_recv(internal) RETURNS fingerprint128
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE internal AS 'uuid_recv';
+CREATE FUNCTION fingerprint128_send(fingerprint128) RETURNS bytea
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE internal AS 'uuid_send';
+CREATE TYPE fingerprint128 (
+    INPUT = fingerprint128_in,
+    OUTPUT = fingerprint128_out,
+    RECEIVE = fingerprint128_recv,
+    SEND = fingerprint128_send,
+    INTERNALLENGTH = 16,
+    ALIGNMENT = char,
+    STORAGE = plain
+);
+
+CREATE CAST (fingerprint64 AS bigint) WITHOUT FUNCTION;
+CREATE CAST (bigint AS fingerprint64) WITHOUT FUNCTION;
+CREATE CAST (fingerprint128 AS uuid) WITHOUT FUNCTION;
+CREATE CAST (uuid AS fingerprint128) WITHOUT FUNCTION;
+
+CREATE FUNCTION fingerprint64_from_bytea(bytea) RETURNS fingerprint64
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', 'fingerprint64_from_bytea';
+CREATE FUNCTION fingerprint128_to_bytea(fingerprint128) RETURNS bytea
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', 'fingerprint128_to_bytea';
+CREATE FUNCTION fingerprint128_from_bytea(bytea) RETURNS fingerprint128
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', 'fingerprint128_from_bytea';
+CREATE CAST (fingerprint64 AS bytea) WITH FUNCTION fingerprint64_send(fingerprint64);
+CREATE CAST (bytea AS fingerprint64) WITH FUNCTION fingerprint64_from_bytea(bytea);
+CREATE CAST (fingerprint128 AS bytea) WITH FUNCTION fingerprint128_to_bytea(fingerprint128);
+CREATE CAST (bytea AS fingerprint128) WITH FUNCTION fingerprint128_from_bytea(bytea);
+
+CREATE FUNCTION fingerprint64_eq(fingerprint64, fingerprint64) RETURNS bool
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE internal AS 'int8eq';
+CREATE FUNCTION fingerprint64_ne(fingerprint64, fingerprint64) RETURNS bool
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE internal AS 'int8ne';
+CREATE FUNCTION fingerprint64_lt(fingerprint64, fingerprint64) RETURNS bool
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', 'fingerprint64_lt';
+CREATE FUNCTION fingerprint64_le(fingerprint64, fingerprint64) RETURNS bool
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', 'fingerprint64_le';
+CREATE FUNCTION fingerprint64_gt(fingerprint64, fingerprint64) RETURNS bool
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', 'fingerprint64_gt';
+CREATE FUNCTION fingerprint64_ge(fingerprint64, fingerprint64) RETURNS bool
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', 'fingerprint64_ge';
+CREATE FUNCTION fingerprint64_cmp(fingerprint64, fingerprint64) RETURNS int4
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', 'fingerprint64_cmp';
+CREATE FUNCTION fingerprint64_hash(fingerprint64) RETURNS int4
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE internal AS 'hashint8';
+CREATE FUNCTION fingerprint64_hash_extended(fingerprint64, bigint) RETURNS bigint
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE internal AS 'hashint8extended';
+CREATE FUNCTION fingerprint64_xor(fingerprint64, fingerprint64) RETURNS fingerprint64
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE internal AS 'int8xor';
+
+CREATE FUNCTION fingerprint128_eq(fingerprint128, fingerprint128) RETURNS bool
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE internal AS 'uuid_eq';
+CREATE FUNCTION fingerprint128_ne(fingerprint128, fingerprint128) RETURNS bool
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE internal AS 'uuid_ne';
+CREATE FUNCTION fingerprint128_lt(fingerprint128, fingerprint128) RETURNS bool
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', 'fingerprint128_lt';
+CREATE FUNCTION fingerprint128_le(fingerprint128, fingerprint128) RETURNS bool
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', 'fingerprint128_le';
+CREATE FUNCTION fingerprint128_gt(fingerprint128, fingerprint128) RETURNS bool
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', 'fingerprint128_gt';
+CREATE FUNCTION fingerprint128_ge(fingerprint128, fingerprint128) RETURNS bool
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', 'fingerprint128_ge';
+CREATE FUNCTION fingerprint128_cmp(fingerprint128, fingerprint128) RETURNS int4
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', 'fingerprint128_cmp';
+CREATE FUNCTION fingerprint128_hash(fingerprint128) RETURNS int4
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE internal AS 'uuid_hash';
+CREATE FUNCTION fingerprint128_hash_extended(fingerprint128, bigint) RETURNS bigint
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE internal AS 'uuid_hash_extended';
+CREATE FUNCTION fingerprint128_xor(fingerprint128, fingerprint128) RETURNS fingerprint128
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE c AS 'MODULE_PATHNAME', 'fingerprint128_xor';
+
+CREATE OPERATOR = (
+    LEFTARG = fingerprint64, RIGHTARG = fingerprint64, FUNCTION = fingerprint64_eq,
+    COMMUTATOR = =, NEGATOR = <>, RESTRICT = eqsel, JOIN = eqjoinsel, HASHES, MERGES
+);
+CREATE OPERATOR <> (
+    LEFTARG = fingerprint64, RIGHTARG = fingerprint64, FUNCTION = fingerprint64_ne,
+    COMMUTATOR = <>, NEGATOR = =, RESTRICT = neqsel, JOIN = neqjoinsel
+);
+CREATE OPERATOR < (
+    LEFTARG = fingerprint64, RIGHTARG = fingerprint64, FUNCTION = fingerprint64_lt,
+    COMMUTATOR = >, NEGATOR = >=, RESTRICT = scalarltsel, JOIN = scalarltjoinsel
+);
+CREATE OPERATOR <= (
+    LEFTARG = fingerprint64, RIGHTARG = fingerprint64, FUNCTION = fingerprint64_le,
+    COMMUTATOR = >=, NEGATOR = >, RESTRICT = scalarlesel, JOIN = scalarlejoinsel
+);
+CREATE OPERATOR > (
+    LEFTARG = fingerprint64, RIGHTARG = fingerprint64, FUNCTION = fingerprint64_gt,
+    COMMUTATOR = <, NEGATOR = <=, RESTRICT = scalargtsel, JOIN = scalargtjoinsel
+);
+CREATE OPERATOR >= (
+    LEFTARG = fingerprint64, RIGHTARG = fingerprint64, FUNCTION = fingerprint64_ge,
+    COMMUTATOR = <=, NEGATOR = <, RESTRICT = scalargesel, JOIN = scalargejoinsel
+);
+CREATE OPERATOR # (
+    LEFTARG = fingerprint64, RIGHTARG = fingerprint64, FUNCTION = fingerprint64_xor,
+    COMMUTATOR = #
+);
+
+CREATE OPERATOR = (
+    LEFTARG = fingerprint128, RIGHTARG = fingerprint128, FUNCTION = fingerprint128_eq,
+    COMMUTATOR = =, NEGATOR = <>, RESTRICT = eqsel, JOIN = eqjoinsel, HASHES, MERGES
+);
+CREATE OPERATOR <> (
+    LEFTARG = fingerprint128, RIGHTARG = fingerprint128, FUNCTION = fingerprint128_ne,
+    COMMUTATOR = <>, NEGATOR = =, RESTRICT = neqsel, JOIN = neqjoinsel
+);
+CREATE OPERATOR < (
+    LEFTARG = fingerprint128, RIGHTARG = fingerprint128, FUNCTION = fingerprint128_lt,
+    COMMUTATOR = >, NEGATOR = >=, RESTRICT = scalarltsel, JOIN = scalarltjoinsel
+);
+CREATE OPERATOR <= (
+    LEFTARG = fingerprint128, RIGHTARG = fingerprint128, FUNCTION = fingerprint128_le,
+    COMMUTATOR = >=, NEGATOR = >, RESTRICT = scalarlesel, JOIN = scalarlejoinsel
+);
+CREATE OPERATOR > (
+    LEFTARG = fingerprint128, RIGHTARG = fingerprint128, FUNCTION = fingerprint128_gt,
+    COMMUTATOR = <, NEGATOR = <=, RESTRICT = scalargtsel, JOIN = scalargtjoinsel
+);
+CREATE OPERATOR >= (
+    LEFTARG = fingerprint128, RIGHTARG = fingerprint128, FUNCTION = fingerprint128_ge,
+    COMMUTATOR = <=, NEGATOR = <, RESTRICT = scalargesel, JOIN = scalargejoinsel
+);
+CREATE OPERATOR # (
+    LEFTARG = fingerprint128, RIGHTARG = fingerprint128, FUNCTION = fingerprint128_xor,
+    COMMUTATOR = #
+);
+
+CREATE OPERATOR CLASS fingerprint64_ops DEFAULT FOR TYPE fingerprint64 USING btree AS
+    OPERATOR 1 <,
+    OPERATOR 2 <=,
+    OPERATOR 3 =,
+    OPERATOR 4 >=,
+    OPERATOR 5 >,
+    FUNCTION 1 fingerprint64_cmp(fingerprint64, fingerprint64);
+CREATE OPERATOR CLASS fingerprint64_ops DEFAULT FOR TYPE fingerprint64 USING hash AS
+    OPERATOR 1 =,
+    FUNCTION 1 fingerprint64_hash(fingerprint64),
+    FUNCTION 2 fingerprint64_hash_extended(fingerprint64, bigint);
+
+CREATE OPERATOR CLASS fingerprint128_ops DEFAULT FOR TYPE fingerprint128 USING btree AS
+    OPERATOR 1 <,
+    OPERATOR 2 <=,
+    OPERATOR 3 =,
+    OPERATOR 4 >=,
+    OPERATOR 5 >,
+    FUNCTION 1 fingerprint128_cmp(fingerprint128, fingerprint128);
+CREATE OPERATOR CLASS fingerprint128_ops DEFAULT FOR TYPE fingerprint128 USING hash AS
+    OPERATOR 1 =,
+    FUNCTION 1 fingerprint128_hash(fingerprint128),
+    FUNCTION 2 fingerprint128_hash_extended(fingerprint128, bigint);
+
+CREATE AGGREGATE bit_xor(fingerprint64) (
+    SFUNC = fingerprint64_xor, STYPE = fingerprint64, COMBINEFUNC = fingerprint64_xor, PARALLEL = SAFE
+);
+CREATE AGGREGATE bit_xor(fingerprint128) (
+    SFUNC = fingerprint128_xor, STYPE = fingerprint128, COMBINEFUNC = fingerprint128_xor, PARALLEL = SAFE
+);
+
+CREATE OR REPLACE FUNCTION id_fingerprint64(VARIADIC text[]) RETURNS fingerprint64
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE sql AS 'SELECT id_seahash(VARIADIC $1)::fingerprint64';
+CREATE OR REPLACE FUNCTION id_fingerprint128(VARIADIC text[]) RETURNS fingerprint128
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE sql AS 'SELECT id_farmhash(VARIADIC $1)::fingerprint128';
+CREATE OR REPLACE FUNCTION checksum_fingerprint64(VARIADIC text[]) RETURNS fingerprint64
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE sql AS 'SELECT checksum_seahash(VARIADIC $1)::fingerprint64';
+CREATE OR REPLACE FUNCTION checksum_fingerprint128(VARIADIC text[]) RETURNS fingerprint128
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE sql AS 'SELECT checksum_farmhash(VARIADIC $1)::fingerprint128';
+CREATE OR REPLACE FUNCTION checksum_fingerprint64_extendable(VARIADIC text[]) RETURNS fingerprint64
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE sql AS 'SELECT checksum_seahash_extendable(VARIADIC $1)::fingerprint64';
+CREATE OR REPLACE FUNCTION checksum_fingerprint128_extendable(VARIADIC text[]) RETURNS fingerprint128
+    STRICT IMMUTABLE PARALLEL SAFE LANGUAGE sql AS 'SELECT checksum_farmhash_extendable(VARIADIC $1)::fingerprint128';
+"#,
+    name = "fingerprint_types",
+    requires = [
+        id_seahash,
+        id_farmhash,
+        checksum_seahash,
+        checksum_farmhash,
+        checksum_seahash_extendable,
+        checksum_farmhash_extendable,
+    ],
+);
+
+pg_finfo_v1!(
+    pg_finfo_fingerprint64_in,
+    pg_finfo_fingerprint64_out,
+    pg_finfo_fingerprint64_from_bytea,
+    pg_finfo_fingerprint64_lt,
+    pg_finfo_fingerprint64_le,
+    pg_finfo_fingerprint64_gt,
+    pg_finfo_fingerprint64_ge,
+    pg_finfo_fingerprint64_cmp,
+    pg_finfo_fingerprint128_in,
+    pg_finfo_fingerprint128_out,
+    pg_finfo_fingerprint128_to_bytea,
+    pg_finfo_fingerprint128_from_bytea,
+    pg_finfo_fingerprint128_lt,
+    pg_finfo_fingerprint128_le,
+    pg_finfo_fingerprint128_gt,
+    pg_finfo_fingerprint128_ge,
+    pg_finfo_fingerprint128_cmp,
+    pg_finfo_fingerprint128_xor
+);
+
+/// Argument `num` of a strict function.
+unsafe fn arg<T: FromDatum>(fcinfo: pg_sys::FunctionCallInfo, num: usize) -> T {
+    unsafe { pg_getarg(fcinfo, num) }.expect("strict functions get no NULL arguments")
+}
+
+/// Comparison functions `$lt`, `$le`, `$gt`, `$ge` and the btree support function `$cmp` of the
+/// order returned by `$order`.
+macro_rules! comparison_functions {
+    ($order:ident, $lt:ident, $le:ident, $gt:ident, $ge:ident, $cmp:ident) => {
+        comparison_functions!(@bool $order, $lt, Ordering::is_lt);
+        comparison_functions!(@bool $order, $le, Ordering::is_le);
+        comparison_functions!(@bool $order, $gt, Ordering::is_gt);
+        comparison_functions!(@bool $order, $ge, Ordering::is_ge);
+
+        #[pg_guard]
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C-unwind" fn $cmp(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+            pg_sys::Datum::from(unsafe { $order(fcinfo) } as i32)
+        }
+    };
+    (@bool $order:ident, $name:ident, $test:path) => {
+        #[pg_guard]
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C-unwind" fn $name(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+            pg_sys::Datum::from($test(unsafe { $order(fcinfo) }))
+        }
+    };
+}
+
+/// Order of two `fingerprint64`s, the one of their unsigned 64 bit integers.
+unsafe fn fingerprint64_order(fcinfo: pg_sys::FunctionCallInfo) -> Ordering {
+    let (a, b) = unsafe { (arg::<i64>(fcinfo, 0), arg::<i64>(fcinfo, 1)) };
+    (a as u64).cmp(&(b as u64))
+}
+
+/// Order of two `fingerprint128`s, the one of their unsigned 128 bit integers.
+unsafe fn fingerprint128_order(fcinfo: pg_sys::FunctionCallInfo) -> Ordering {
+    let (a, b) = unsafe { (arg::<Uuid>(fcinfo, 0), arg::<Uuid>(fcinfo, 1)) };
+    u128::from_le_bytes(*a.as_bytes()).cmp(&u128::from_le_bytes(*b.as_bytes()))
+}
+
+comparison_functions!(
+    fingerprint64_order,
+    fingerprint64_lt,
+    fingerprint64_le,
+    fingerprint64_gt,
+    fingerprint64_ge,
+    fingerprint64_cmp
+);
+comparison_functions!(
+    fingerprint128_order,
+    fingerprint128_lt,
+    fingerprint128_le,
+    fingerprint128_gt,
+    fingerprint128_ge,
+    fingerprint128_cmp
+);
+
+/// A `cstring` datum of `text`, as returned by output functions.
+fn cstring_datum(text: String) -> pg_sys::Datum {
+    let cstring = CString::new(text).expect("hex digits are not NUL");
+    pg_sys::Datum::from(unsafe { pg_sys::pstrdup(cstring.as_ptr()) })
+}
+
+#[pg_guard]
+#[unsafe(no_mangle)]
+/// `fingerprint64` of its hexadecimal, see `fingerprint_from_hex`.
+pub unsafe extern "C-unwind" fn fingerprint64_in(
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> pg_sys::Datum {
+    let input = unsafe { arg::<&CStr>(fcinfo, 0) };
+    match input.to_str().ok().and_then(hex::from_hex64) {
+        Some(fingerprint) => pg_sys::Datum::from(seahash_bigint(fingerprint)),
+        None => error!(
+            "invalid input syntax for type fingerprint64: \"{}\"",
+            input.to_string_lossy()
+        ),
+    }
+}
+
+#[pg_guard]
+#[unsafe(no_mangle)]
+/// Hexadecimal of a `fingerprint64`, see `fingerprint_to_hex`.
+pub unsafe extern "C-unwind" fn fingerprint64_out(
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> pg_sys::Datum {
+    let fingerprint = unsafe { arg::<i64>(fcinfo, 0) };
+    cstring_datum(hex::to_hex64(fingerprint as u64))
+}
+
+#[pg_guard]
+#[unsafe(no_mangle)]
+/// `fingerprint64` of its 8 bytes in network byte order, like `int8send` of the `bigint`.
+pub unsafe extern "C-unwind" fn fingerprint64_from_bytea(
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> pg_sys::Datum {
+    let bytes = unsafe { arg::<&[u8]>(fcinfo, 0) };
+    match <[u8; 8]>::try_from(bytes) {
+        Ok(bytes) => pg_sys::Datum::from(i64::from_be_bytes(bytes)),
+        Err(_) => error!("fingerprint64 has 8 bytes, got {}", bytes.len()),
+    }
+}
+
+#[pg_guard]
+#[unsafe(no_mangle)]
+/// `fingerprint128` of its hexadecimal, see `fingerprint128_from_hex`.
+pub unsafe extern "C-unwind" fn fingerprint128_in(
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> pg_sys::Datum {
+    let input = unsafe { arg::<&CStr>(fcinfo, 0) };
+    match input.to_str().ok().and_then(hex::from_hex128) {
+        Some(fingerprint) => farmhash_uuid(fingerprint)
+            .into_datum()
+            .expect("uuid is not NULL"),
+        None => error!(
+            "invalid input syntax for type fingerprint128: \"{}\"",
+            input.to_string_lossy()
+        ),
+    }
+}
+
+#[pg_guard]
+#[unsafe(no_mangle)]
+/// Hexadecimal of a `fingerprint128`, see `fingerprint_to_hex`.
+pub unsafe extern "C-unwind" fn fingerprint128_out(
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> pg_sys::Datum {
+    let fingerprint = unsafe { arg::<Uuid>(fcinfo, 0) };
+    cstring_datum(hex::to_hex128(u128::from_le_bytes(*fingerprint.as_bytes())))
+}
+
+#[pg_guard]
+#[unsafe(no_mangle)]
+/// The 16 bytes of a `fingerprint128` in network byte order, like `fingerprint64`'s.
+pub unsafe extern "C-unwind" fn fingerprint128_to_bytea(
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> pg_sys::Datum {
+    let fingerprint = unsafe { arg::<Uuid>(fcinfo, 0) };
+    u128::from_le_bytes(*fingerprint.as_bytes())
+        .to_be_bytes()
+        .as_slice()
+        .into_datum()
+        .expect("bytea is not NULL")
+}
+
+#[pg_guard]
+#[unsafe(no_mangle)]
+/// `fingerprint128` of its 16 bytes in network byte order.
+pub unsafe extern "C-unwind" fn fingerprint128_from_bytea(
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> pg_sys::Datum {
+    let bytes = unsafe { arg::<&[u8]>(fcinfo, 0) };
+    match <[u8; 16]>::try_from(bytes) {
+        Ok(bytes) => farmhash_uuid(u128::from_be_bytes(bytes))
+            .into_datum()
+            .expect("uuid is not NULL"),
+        Err(_) => error!("fingerprint128 has 16 bytes, got {}", bytes.len()),
+    }
+}
+
+#[pg_guard]
+#[unsafe(no_mangle)]
+/// Bitwise XOR of two `fingerprint128`s.
+pub unsafe extern "C-unwind" fn fingerprint128_xor(
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> pg_sys::Datum {
+    let (a, b) = unsafe { (arg::<Uuid>(fcinfo, 0), arg::<Uuid>(fcinfo, 1)) };
+    let xor = u128::from_ne_bytes(*a.as_bytes()) ^ u128::from_ne_bytes(*b.as_bytes());
+    Uuid::from_bytes(xor.to_ne_bytes())
+        .into_datum()
+        .expect("uuid is not NULL")
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pg_schema]
+mod tests {
+    use pgrx::prelude::*;
+
+    #[pg_test]
+    fn pg_test_fingerprint_types() {
+        for (query, golden) in [
+            (
+                "SELECT id_fingerprint64('a', 'b', 'c')::text;",
+                "c67e2658b8df559a",
+            ),
+            (
+                "SELECT 'C67E2658B8DF559A'::fingerprint64::bigint::text;",
+                "-4143832444633655910",
+            ),
+            ("SELECT '0x1'::fingerprint64::text;", "0000000000000001"),
+            (
+                "SELECT '1'::fingerprint128::text;",
+                "00000000000000000000000000000001",
+            ),
+            (
+                "SELECT ('ff'::fingerprint64 # '0f'::fingerprint64)::text;",
+                "00000000000000f0",
+            ),
+            (
+                "SELECT ('ff'::fingerprint128 # '0f'::fingerprint128)::text;",
+                "000000000000000000000000000000f0",
+            ),
+            (
+                "SELECT bit_xor(f)::text FROM (VALUES ('ff'::fingerprint64), ('0f'), ('f0')) s(f);",
+                "0000000000000000",
+            ),
+        ] {
+            let result = Spi::get_one::<String>(query);
+            assert_eq!(result, Ok(Some(golden.to_string())), "using {query}");
+        }
+
+        for query in [
+            // the text representation is the one of fingerprint_to_hex
+            "SELECT bool_and(id_fingerprint64(i::text)::text = fingerprint_to_hex(id_seahash(i::text))
+                AND id_fingerprint128(i::text)::text = fingerprint_to_hex(id_farmhash(i::text))
+                AND id_fingerprint64(i::text)::text::fingerprint64 = id_fingerprint64(i::text)
+                AND id_fingerprint128(i::text)::text::fingerprint128 = id_fingerprint128(i::text))
+             FROM generate_series(1, 100) i;",
+            // the families return the existing fingerprints
+            "SELECT checksum_fingerprint64('k', 'v', 'n', NULL)::bigint = checksum_seahash('k', 'v', 'n', NULL)
+                AND checksum_fingerprint128('k', 'v', 'n', NULL)::uuid = checksum_farmhash('k', 'v', 'n', NULL)
+                AND checksum_fingerprint64_extendable('k', 'v', 'n', NULL) = checksum_fingerprint64('k', 'v')
+                AND checksum_fingerprint128_extendable('k', 'v', 'n', NULL) = checksum_fingerprint128('k', 'v');",
+            // bytea casts round-trip, the bytes of the hexadecimal
+            "SELECT bool_and(id_fingerprint64(i::text)::bytea = int8send(id_seahash(i::text))
+                AND id_fingerprint64(i::text)::bytea = decode(id_fingerprint64(i::text)::text, 'hex')
+                AND id_fingerprint128(i::text)::bytea = decode(id_fingerprint128(i::text)::text, 'hex')
+                AND id_fingerprint64(i::text)::bytea::fingerprint64 = id_fingerprint64(i::text)
+                AND id_fingerprint128(i::text)::bytea::fingerprint128 = id_fingerprint128(i::text))
+             FROM generate_series(1, 100) i;",
+            "SELECT bit_xor(id_fingerprint128(i::text))::uuid = bit_xor(id_farmhash(i::text))
+             FROM generate_series(1, 100) i;",
+            // ordered like the hexadecimal, i.e. the unsigned integers
+            "SELECT 'ffffffffffffffff'::fingerprint64 > '7fffffffffffffff'::fingerprint64
+                AND '1'::fingerprint64 < '2'::fingerprint64
+                AND '1'::fingerprint128 < '100'::fingerprint128
+                AND 'ff000000000000000000000000000000'::fingerprint128 > '1'::fingerprint128;",
+            "SELECT array_agg(f ORDER BY f)::text[] = array_agg(f::text ORDER BY f::text COLLATE \"C\")
+                AND array_agg(g ORDER BY g)::text[] = array_agg(g::text ORDER BY g::text COLLATE \"C\")
+             FROM (SELECT id_fingerprint64(i::text), id_fingerprint128(i::text)
+                   FROM generate_series(1, 1000) i) s(f, g);",
+        ] {
+            let result = Spi::get_one::<bool>(query);
+            assert_eq!(result, Ok(Some(true)), "using {query}");
+        }
+
+        Spi::run(
+            "CREATE TABLE fingerprint_types_test (f64 fingerprint64 PRIMARY KEY, f128 fingerprint128 UNIQUE);
+             CREATE INDEX ON fingerprint_types_test USING hash (f128);
+             INSERT INTO fingerprint_types_test
+                SELECT id_fingerprint64(i::text), id_fingerprint128(i::text) FROM generate_series(1, 1000) i;
+             SET LOCAL enable_seqscan = off;",
+        )
+        .expect("SPI failed");
+        for (query, golden) in [
+            (
+                "SELECT count(*) FROM fingerprint_types_test
+                 WHERE f64 = id_fingerprint64('1') OR f128 = id_fingerprint128('2');",
+                2,
+            ),
+            (
+                "SELECT count(DISTINCT f128) FROM fingerprint_types_test a
+                 JOIN fingerprint_types_test b USING (f64, f128);",
+                1000,
+            ),
+        ] {
+            let result = Spi::get_one::<i64>(query);
+            assert_eq!(result, Ok(Some(golden)), "using {query}");
+        }
+    }
+}